@@ -0,0 +1,10 @@
+-- History of judges being excluded from / reinstated into the tabulation
+CREATE TABLE IF NOT EXISTS judge_exclusion_logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    judge_id UUID NOT NULL REFERENCES judges (id) ON DELETE CASCADE,
+    excluded BOOLEAN NOT NULL,
+    reason TEXT NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS judge_exclusion_logs_judge_id_idx ON judge_exclusion_logs (judge_id);
@@ -0,0 +1,6 @@
+-- Judges should not score the candidates of a college they are affiliated with
+CREATE TABLE IF NOT EXISTS judge_college_affiliations (
+    judge_id UUID NOT NULL REFERENCES judges (id) ON DELETE CASCADE,
+    college_id TEXT NOT NULL REFERENCES college (college_id) ON DELETE CASCADE,
+    PRIMARY KEY (judge_id, college_id)
+);
@@ -0,0 +1,23 @@
+-- Frozen copies of the results, taken every time they are published
+CREATE TABLE IF NOT EXISTS results_snapshots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    published_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    method TEXT NOT NULL,
+    ranking JSONB NOT NULL,
+    breakdown JSONB NOT NULL,
+    judges JSONB NOT NULL,
+    content_hash TEXT NOT NULL
+);
+
+-- Once published, a snapshot can never be changed or removed
+CREATE OR REPLACE FUNCTION results_snapshots_immutable() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'results snapshots are immutable';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS results_snapshots_immutable ON results_snapshots;
+
+CREATE TRIGGER results_snapshots_immutable
+    BEFORE UPDATE OR DELETE ON results_snapshots
+    FOR EACH ROW EXECUTE FUNCTION results_snapshots_immutable();
@@ -0,0 +1,33 @@
+-- Append-only log of every score write, each entry hashes the one before it in the same event
+CREATE TABLE IF NOT EXISTS score_chain (
+    id BIGSERIAL PRIMARY KEY,
+    event_id UUID NOT NULL REFERENCES events(id),
+    score_id UUID NOT NULL,
+    action TEXT NOT NULL,
+    score INTEGER NOT NULL,
+    max INTEGER NOT NULL,
+    candidate_id UUID NOT NULL,
+    criteria_id UUID NOT NULL,
+    category_id UUID NOT NULL,
+    judge_id UUID NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL,
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL UNIQUE
+);
+
+CREATE INDEX IF NOT EXISTS score_chain_event_id_idx ON score_chain (event_id, id);
+
+CREATE OR REPLACE FUNCTION score_chain_append_only() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'score chain entries cannot be changed or removed';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS score_chain_append_only ON score_chain;
+
+CREATE TRIGGER score_chain_append_only
+    BEFORE UPDATE OR DELETE ON score_chain
+    FOR EACH ROW EXECUTE FUNCTION score_chain_append_only();
+
+-- Published results also record where the chain was at the time
+ALTER TABLE results_snapshots ADD COLUMN IF NOT EXISTS chain_heads JSONB NOT NULL DEFAULT '[]';
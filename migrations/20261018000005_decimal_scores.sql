@@ -0,0 +1,17 @@
+-- Scores can have up to two decimal places, e.g. 8.5 on a 10-point criteria
+ALTER TABLE criterias ALTER COLUMN max_score TYPE NUMERIC(6, 2);
+
+-- Smallest increment a judge can score with on a criteria, e.g. 0.5 for half points
+ALTER TABLE criterias
+    ADD COLUMN IF NOT EXISTS score_step NUMERIC(4, 2) NOT NULL DEFAULT 1 CHECK (score_step > 0);
+
+ALTER TABLE scores
+    ALTER COLUMN score TYPE NUMERIC(6, 2),
+    ALTER COLUMN max TYPE NUMERIC(6, 2);
+
+ALTER TABLE score_chain
+    ALTER COLUMN score TYPE NUMERIC(6, 2),
+    ALTER COLUMN max TYPE NUMERIC(6, 2);
+
+-- Weights are fractions of the event's total, e.g. 0.3 for 30%
+ALTER TABLE categories ALTER COLUMN weight TYPE NUMERIC(5, 4);
@@ -0,0 +1,2 @@
+-- How the final scores in a snapshot were rounded
+ALTER TABLE results_snapshots ADD COLUMN IF NOT EXISTS rounding JSONB;
@@ -0,0 +1,4 @@
+-- Optional share of the category a criteria is worth, e.g. 0.3 for 30%. Only used when every
+-- criteria of the category has one, otherwise criterias count in proportion to their max score.
+ALTER TABLE criterias
+    ADD COLUMN IF NOT EXISTS weight NUMERIC(5, 4) CHECK (weight > 0 AND weight <= 1);
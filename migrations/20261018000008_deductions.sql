@@ -0,0 +1,14 @@
+-- Penalties entered by the tabulator, e.g. for overtime or wardrobe violations. Points are taken
+-- off the candidate's score in the category, out of 100.
+CREATE TABLE IF NOT EXISTS deductions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    candidate_id UUID NOT NULL REFERENCES candidates(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    points NUMERIC(6, 2) NOT NULL CHECK (points > 0),
+    reason TEXT NOT NULL,
+    approved_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS deductions_candidate_category_idx
+    ON deductions (candidate_id, category_id);
@@ -0,0 +1,26 @@
+-- Where an event is in its lifecycle, replaces the bare active_event flag. active_event is kept
+-- in sync with status = 'live' for older clients.
+DO $$
+BEGIN
+    CREATE TYPE event_status AS ENUM ('draft', 'setup', 'live', 'closed', 'archived');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END
+$$;
+
+ALTER TABLE events
+    ADD COLUMN IF NOT EXISTS status event_status NOT NULL DEFAULT 'draft';
+
+-- Existing events were already set up, the active one is live and the rest that were scored
+-- are over
+UPDATE events e
+SET status = CASE
+    WHEN e.active_event THEN 'live'::event_status
+    WHEN EXISTS (
+        SELECT 1 FROM scores s
+        JOIN categories c ON c.id = s.category_id
+        WHERE c.event_id = e.id
+    ) THEN 'closed'::event_status
+    ELSE 'setup'::event_status
+END
+WHERE e.status = 'draft';
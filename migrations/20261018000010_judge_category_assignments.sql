@@ -0,0 +1,10 @@
+-- Panels per category, e.g. separate judges for the pre-pageant interview. A category without
+-- any assignments is scored by every judge of its event.
+CREATE TABLE IF NOT EXISTS judge_category_assignments (
+    judge_id UUID NOT NULL REFERENCES judges (id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+    PRIMARY KEY (judge_id, category_id)
+);
+
+CREATE INDEX IF NOT EXISTS judge_category_assignments_category_idx
+    ON judge_category_assignments (category_id);
@@ -0,0 +1,7 @@
+-- How much a judge's scores count relative to the rest of the panel, e.g. 2 for a head judge.
+-- A weight on an assignment overrides the judge's weight for that category.
+ALTER TABLE judges
+    ADD COLUMN IF NOT EXISTS weight NUMERIC(5, 2) NOT NULL DEFAULT 1 CHECK (weight > 0);
+
+ALTER TABLE judge_category_assignments
+    ADD COLUMN IF NOT EXISTS weight NUMERIC(5, 2) CHECK (weight > 0);
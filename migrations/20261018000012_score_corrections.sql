@@ -0,0 +1,31 @@
+-- Once a category is locked its scores can only change through a correction request that a
+-- tabulator approves
+ALTER TABLE categories
+    ADD COLUMN IF NOT EXISTS locked_at TIMESTAMPTZ;
+
+DO $$
+BEGIN
+    CREATE TYPE correction_status AS ENUM ('pending', 'approved', 'rejected');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END
+$$;
+
+CREATE TABLE IF NOT EXISTS score_corrections (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    score_id UUID NOT NULL REFERENCES scores (id) ON DELETE CASCADE,
+    judge_id UUID NOT NULL REFERENCES judges (id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+    previous_score NUMERIC(6, 2) NOT NULL,
+    requested_score NUMERIC(6, 2) NOT NULL,
+    reason TEXT NOT NULL,
+    status correction_status NOT NULL DEFAULT 'pending',
+    reviewed_by TEXT,
+    review_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    reviewed_at TIMESTAMPTZ
+);
+
+-- Only one open request per score at a time
+CREATE UNIQUE INDEX IF NOT EXISTS score_corrections_pending_idx
+    ON score_corrections (score_id) WHERE status = 'pending';
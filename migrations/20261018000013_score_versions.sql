@@ -0,0 +1,4 @@
+-- Bumped on every change to a score, updates have to say which version they edited so that
+-- concurrent edits don't silently overwrite each other
+ALTER TABLE scores
+    ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,3 @@
+-- The exact bytes the content hash of a snapshot was computed over, so verifying it never
+-- depends on how the snapshot would be serialized today
+ALTER TABLE results_snapshots ADD COLUMN IF NOT EXISTS content TEXT;
@@ -0,0 +1,17 @@
+-- Deductions that were taken back, kept as they were when deleted along with who did it and why
+CREATE TABLE IF NOT EXISTS deduction_logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    deduction_id UUID NOT NULL,
+    candidate_id UUID NOT NULL REFERENCES candidates(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    points NUMERIC(6, 2) NOT NULL,
+    reason TEXT NOT NULL,
+    approved_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    deleted_by TEXT NOT NULL,
+    delete_reason TEXT NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS deduction_logs_candidate_category_idx
+    ON deduction_logs (candidate_id, category_id);
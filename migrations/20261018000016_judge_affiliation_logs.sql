@@ -0,0 +1,11 @@
+-- History of conflicts of interest being declared / withdrawn, they change the results as much
+-- as excluding a judge does
+CREATE TABLE IF NOT EXISTS judge_affiliation_logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    judge_id UUID NOT NULL REFERENCES judges (id) ON DELETE CASCADE,
+    college_id TEXT NOT NULL,
+    affiliated BOOLEAN NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS judge_affiliation_logs_judge_id_idx ON judge_affiliation_logs (judge_id);
@@ -0,0 +1,51 @@
+-- Scores written before the chain existed get an entry of their own, appended to the end of
+-- their event's chain exactly like chain::append does it, so verifying an upgraded database
+-- doesn't report them as tampered with
+DO $$
+DECLARE
+    s RECORD;
+    prev TEXT;
+    score_text TEXT;
+    max_text TEXT;
+    recorded TIMESTAMPTZ := now();
+BEGIN
+    FOR s IN
+        SELECT sc.id, sc.score, sc.max, sc.candidate_id, sc.criteria_id, sc.category_id,
+            sc.judge_id, c.event_id
+        FROM scores sc
+        JOIN categories c ON c.id = sc.category_id
+        WHERE NOT EXISTS (SELECT 1 FROM score_chain ch WHERE ch.score_id = sc.id)
+        ORDER BY c.event_id, sc.time_of_scoring, sc.id
+    LOOP
+        prev := NULL;
+
+        SELECT hash INTO prev FROM score_chain
+        WHERE event_id = s.event_id
+        ORDER BY id DESC
+        LIMIT 1;
+
+        -- Same as Decimal::normalize, 8.50 -> 8.5 and 10.00 -> 10
+        score_text := s.score::TEXT;
+        IF position('.' IN score_text) > 0 THEN
+            score_text := regexp_replace(score_text, '\.?0+$', '');
+        END IF;
+
+        max_text := s.max::TEXT;
+        IF position('.' IN max_text) > 0 THEN
+            max_text := regexp_replace(max_text, '\.?0+$', '');
+        END IF;
+
+        prev := COALESCE(prev, repeat('0', 64));
+
+        INSERT INTO score_chain (event_id, score_id, action, score, max, candidate_id,
+            criteria_id, category_id, judge_id, recorded_at, prev_hash, hash)
+        VALUES (s.event_id, s.id, 'backfill', s.score, s.max, s.candidate_id, s.criteria_id,
+            s.category_id, s.judge_id, recorded, prev,
+            encode(sha256(convert_to(concat_ws('|',
+                prev, s.event_id, s.id, 'backfill', score_text, max_text, s.candidate_id,
+                s.criteria_id, s.category_id, s.judge_id,
+                to_char(recorded AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"Z"')
+            ), 'UTF8')), 'hex'));
+    END LOOP;
+END
+$$;
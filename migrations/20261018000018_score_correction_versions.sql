@@ -0,0 +1,3 @@
+-- The version of the score a correction was filed against, approving it after the score was
+-- changed some other way would overwrite that change. Requests filed before this have none.
+ALTER TABLE score_corrections ADD COLUMN IF NOT EXISTS score_version INTEGER;
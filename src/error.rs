@@ -1,52 +1,176 @@
-// NOTE: Not sure if I need this module, will leave it here for now just in case I need custom Errors
-// Might use anyhow instead
-
 // Remove some noise
 #![allow(unused)]
 
+use axum::body::Bytes;
+use axum::extract::FromRequest;
 use axum::http;
 use axum::response::{IntoResponse, Response};
 use rust_xlsxwriter::XlsxError;
+use serde_json::{json, Value};
 
+// Every error a handler can return. Clients should match on code(), which stays the same,
+// the message is only meant to be shown to people.
 #[derive(Debug)]
-pub struct AppError {
-    message: String,
-    code: http::StatusCode,
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    // details carry whatever the client needs to resolve it, e.g. the current value of a score
+    Conflict {
+        message: String,
+        details: Option<Value>,
+    },
+    Validation {
+        message: String,
+        details: Option<Value>,
+    },
+    // The message is only logged, clients get a generic one so nothing about the database leaks
+    Internal(String),
 }
 
 impl AppError {
-    pub fn new(code: http::StatusCode, message: impl Into<String>) -> Self {
-        Self {
-            code,
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        AppError::BadRequest(message.into())
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        AppError::Unauthorized(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        AppError::Forbidden(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict {
             message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::Internal(message.into())
+    }
+
+    pub fn with_details(self, details: Value) -> Self {
+        match self {
+            AppError::Conflict { message, .. } => AppError::Conflict {
+                message,
+                details: Some(details),
+            },
+            AppError::Validation { message, .. } => AppError::Validation {
+                message,
+                details: Some(details),
+            },
+            other => other,
+        }
+    }
+
+    pub fn status(&self) -> http::StatusCode {
+        match self {
+            AppError::BadRequest(_) => http::StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => http::StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => http::StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => http::StatusCode::NOT_FOUND,
+            AppError::Conflict { .. } => http::StatusCode::CONFLICT,
+            AppError::Validation { .. } => http::StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Internal(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict { .. } => "conflict",
+            AppError::Validation { .. } => "validation_failed",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::Internal(_) => "Something went wrong on the server",
+            AppError::BadRequest(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict { message, .. }
+            | AppError::Validation { message, .. } => message,
+        }
+    }
+
+    pub fn details(&self) -> Option<&Value> {
+        match self {
+            AppError::Conflict { details, .. } | AppError::Validation { details, .. } => {
+                details.as_ref()
+            }
+            _ => None,
         }
     }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "code": self.code(),
+            "message": self.message(),
+            "details": self.details(),
+        })
+    }
 }
 
 impl From<sqlx::Error> for AppError {
     fn from(error: sqlx::Error) -> Self {
-        AppError {
-            code: http::StatusCode::INTERNAL_SERVER_ERROR,
-            message: format!("SQLx Error: {}", error),
+        if let sqlx::Error::RowNotFound = error {
+            return AppError::not_found("Not found");
+        }
+
+        if let Some(database_error) = error.as_database_error() {
+            let details = json!({ "constraint": database_error.constraint() });
+
+            // https://www.postgresql.org/docs/current/errcodes-appendix.html
+            match database_error.code().as_deref() {
+                Some("23505") => {
+                    return AppError::conflict("Already exists").with_details(details);
+                }
+                Some("23503") => {
+                    return AppError::validation("Refers to something that doesn't exist")
+                        .with_details(details);
+                }
+                Some("23502") | Some("23514") => {
+                    return AppError::validation("A value is missing or out of range")
+                        .with_details(details);
+                }
+                _ => {}
+            }
         }
+
+        AppError::internal(format!("SQLx Error: {}", error))
     }
 }
 
 impl From<XlsxError> for AppError {
     fn from(error: XlsxError) -> Self {
-        AppError {
-            code: http::StatusCode::INTERNAL_SERVER_ERROR,
-            message: format!("Xlsx Error: {}", error),
-        }
+        AppError::internal(format!("Xlsx Error: {}", error))
     }
 }
 
 impl From<anyhow::Error> for AppError {
     fn from(error: anyhow::Error) -> Self {
-        AppError {
-            code: http::StatusCode::INTERNAL_SERVER_ERROR,
-            message: format!("Anyhow Error: {}", error),
-        }
+        AppError::internal(format!("Anyhow Error: {}", error))
     }
 }
 
@@ -54,6 +178,38 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         println!("->> {self:?}\n");
 
-        (self.code, self.message).into_response()
+        (self.status(), axum::Json(self.to_json())).into_response()
     }
 }
+
+// axum's own extractors (Json, Query, Path) reject a request with a plain text body before the
+// handler runs, this gives those the same JSON body as every other error
+pub async fn map_rejection(response: Response) -> Response {
+    let status = response.status();
+
+    let is_json = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+
+    if is_json || !status.is_client_error() || status == http::StatusCode::NOT_FOUND {
+        return response;
+    }
+
+    let request = http::Request::new(response.into_body());
+    let message = match Bytes::from_request(request, &()).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => status.canonical_reason().unwrap_or_default().to_string(),
+    };
+
+    // Well formed but the wrong shape, e.g. a missing field, is a validation error
+    let error = if status == http::StatusCode::UNPROCESSABLE_ENTITY {
+        AppError::validation(message)
+    } else {
+        AppError::bad_request(message)
+    };
+
+    // Keeps the original status, e.g. 415 for a missing Content-Type
+    (status, axum::Json(error.to_json())).into_response()
+}
@@ -0,0 +1,207 @@
+use axum::{extract, http, response::Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, PgPool};
+
+use crate::error::AppError;
+use crate::realtime::Realtime;
+
+use super::event;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Affiliation {
+    judge_id: uuid::Uuid,
+    college_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAffiliation {
+    college_id: String,
+}
+
+// The tabulation drops and imputes the scores of a judge with a conflict of interest, so
+// declaring or withdrawing one changes the results just like excluding the judge does
+async fn ensure_changes(pool: &PgPool, judge_id: uuid::Uuid) -> Result<uuid::Uuid, AppError> {
+    let event_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT event_id FROM judges WHERE id = ($1)")
+            .bind(judge_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("Judge not found"))?;
+
+    event::ensure_changes(pool, event_id).await?;
+
+    Ok(event_id)
+}
+
+async fn log_change(
+    conn: &mut PgConnection,
+    judge_id: uuid::Uuid,
+    college_id: &str,
+    affiliated: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO judge_affiliation_logs (judge_id, college_id, affiliated) VALUES ($1, $2, $3)",
+    )
+    .bind(judge_id)
+    .bind(college_id)
+    .bind(affiliated)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+fn publish(
+    realtime: &Realtime,
+    event_id: uuid::Uuid,
+    judge_id: uuid::Uuid,
+    college_id: &str,
+    affiliated: bool,
+) {
+    println!(
+        "Judge {} {} college {}\n",
+        judge_id,
+        if affiliated {
+            "affiliated with"
+        } else {
+            "no longer affiliated with"
+        },
+        college_id
+    );
+
+    let notification = json!({
+        "type": "judge_affiliation",
+        "event_id": event_id,
+        "judge_id": judge_id,
+        "college_id": college_id,
+        "affiliated": affiliated,
+    });
+
+    realtime.publish(&notification.to_string());
+}
+
+pub async fn create_affiliation(
+    extract::State(pool): extract::State<PgPool>,
+    extract::State(realtime): extract::State<Realtime>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+    axum::Json(payload): axum::Json<CreateAffiliation>,
+) -> Result<(http::StatusCode, axum::Json<Affiliation>), AppError> {
+    let event_id = ensure_changes(&pool, judge_id).await?;
+
+    let mut txn = pool.begin().await?;
+
+    let affiliation = sqlx::query_as::<_, Affiliation>(
+        r#"
+        INSERT INTO judge_college_affiliations (judge_id, college_id)
+        VALUES ($1, $2)
+        ON CONFLICT (judge_id, college_id) DO UPDATE SET college_id = EXCLUDED.college_id
+        RETURNING *
+        "#,
+    )
+    .bind(judge_id)
+    .bind(&payload.college_id)
+    .fetch_one(&mut *txn)
+    .await?;
+
+    log_change(&mut txn, judge_id, &affiliation.college_id, true).await?;
+
+    txn.commit().await?;
+
+    publish(&realtime, event_id, judge_id, &affiliation.college_id, true);
+
+    Ok((http::StatusCode::CREATED, axum::Json(affiliation)))
+}
+
+pub async fn get_affiliations(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+) -> Result<axum::Json<Vec<Affiliation>>, AppError> {
+    let affiliations = sqlx::query_as::<_, Affiliation>(
+        "SELECT * FROM judge_college_affiliations WHERE judge_id = ($1)",
+    )
+    .bind(judge_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(affiliations))
+}
+
+pub async fn delete_affiliation(
+    extract::State(pool): extract::State<PgPool>,
+    extract::State(realtime): extract::State<Realtime>,
+    extract::Path((judge_id, college_id)): extract::Path<(uuid::Uuid, String)>,
+) -> Result<http::StatusCode, AppError> {
+    let event_id = ensure_changes(&pool, judge_id).await?;
+
+    let mut txn = pool.begin().await?;
+
+    let deleted = sqlx::query(
+        "DELETE FROM judge_college_affiliations WHERE judge_id = ($1) AND college_id = ($2)",
+    )
+    .bind(judge_id)
+    .bind(&college_id)
+    .execute(&mut *txn)
+    .await?;
+
+    if deleted.rows_affected() == 0 {
+        return Err(AppError::not_found(
+            "Judge is not affiliated with the college",
+        ));
+    }
+
+    log_change(&mut txn, judge_id, &college_id, false).await?;
+
+    txn.commit().await?;
+
+    publish(&realtime, event_id, judge_id, &college_id, false);
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AffiliationLog {
+    id: uuid::Uuid,
+    judge_id: uuid::Uuid,
+    college_id: String,
+    affiliated: bool,
+    changed_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub async fn get_affiliation_logs(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+) -> Result<axum::Json<Vec<AffiliationLog>>, AppError> {
+    let logs = sqlx::query_as::<_, AffiliationLog>(
+        "SELECT * FROM judge_affiliation_logs WHERE judge_id = ($1) ORDER BY changed_at DESC",
+    )
+    .bind(judge_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(logs))
+}
+
+// A judge may not score the candidates of a college they're affiliated with
+pub async fn has_conflict(
+    pool: &PgPool,
+    judge_id: uuid::Uuid,
+    candidate_id: uuid::Uuid,
+) -> Result<bool, AppError> {
+    let conflict = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM judge_college_affiliations a
+            JOIN candidates c ON c.college_id = a.college_id
+            WHERE a.judge_id = ($1) AND c.id = ($2)
+        )
+        "#,
+    )
+    .bind(judge_id)
+    .bind(candidate_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(conflict)
+}
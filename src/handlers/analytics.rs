@@ -0,0 +1,445 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::extract::{Query, State};
+use axum::response::Result;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+
+use super::assignment;
+use super::category::Category;
+use super::tabulation::{self, TabulationInput};
+
+const DEFAULT_OUTLIER_THRESHOLD: f64 = 2.0;
+
+#[derive(Debug, FromRow)]
+struct AnalyticsJudge {
+    id: uuid::Uuid,
+    name: String,
+    score_exclusion: bool,
+}
+
+// Sum of a judge's scores for a candidate in a category
+#[derive(Debug, Clone, PartialEq)]
+pub struct JudgeTotal {
+    pub judge_id: uuid::Uuid,
+    pub candidate_id: uuid::Uuid,
+    pub category_id: uuid::Uuid,
+    pub total: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JudgeAnalytics {
+    judge_id: uuid::Uuid,
+    judge_name: String,
+    excluded: bool,
+    category_id: uuid::Uuid,
+    category_name: String,
+    candidates_scored: usize,
+    mean: f64,
+    std_dev: f64,
+    min: f64,
+    max: f64,
+    // Highest total a judge can give in this category
+    max_possible: f64,
+    // How much of the possible range the judge actually used, from 0 to 1
+    range_usage: Option<f64>,
+    // Agreement between the judge's ranking and the final ranking tabulated without them,
+    // from -1 to 1
+    spearman: Option<f64>,
+    kendall: Option<f64>,
+    outliers: Vec<OutlierScore>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutlierScore {
+    candidate_id: uuid::Uuid,
+    candidate_number: i32,
+    score: f64,
+    // Mean of the rest of the panel
+    panel_mean: f64,
+    deviation: f64,
+    z_score: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsParam {
+    event_id: Option<uuid::Uuid>,
+    category_id: Option<uuid::Uuid>,
+    // How many standard deviations away from the panel a score has to be to get flagged
+    threshold: Option<f64>,
+}
+
+// Totals of every judge for every candidate they scored, scores given despite a conflict of
+// interest are left out like they are in the tabulation
+pub fn judge_totals(input: &TabulationInput) -> Vec<JudgeTotal> {
+    let mut totals: HashMap<(uuid::Uuid, uuid::Uuid, uuid::Uuid), f64> = HashMap::new();
+
+    for score in input.scores.iter().filter(|score| {
+        !input
+            .conflicts
+            .contains(&(score.judge_id, score.candidate_id))
+    }) {
+        *totals
+            .entry((score.judge_id, score.candidate_id, score.category_id))
+            .or_default() += score.score.to_f64().unwrap_or_default();
+    }
+
+    let mut totals: Vec<JudgeTotal> = totals
+        .into_iter()
+        .map(
+            |((judge_id, candidate_id, category_id), total)| JudgeTotal {
+                judge_id,
+                candidate_id,
+                category_id,
+                total,
+            },
+        )
+        .collect();
+
+    totals.sort_by_key(|total| (total.category_id, total.judge_id, total.candidate_id));
+
+    totals
+}
+
+// candidate_id -> mean total given by the rest of the panel. A judge is never compared against
+// a consensus that includes them, on a panel of 3 that would hide most of a harsh judge.
+pub fn panel_means_without(
+    totals: &[&JudgeTotal],
+    judge_id: uuid::Uuid,
+    panel: &HashSet<uuid::Uuid>,
+) -> HashMap<uuid::Uuid, f64> {
+    let mut others: HashMap<uuid::Uuid, Vec<f64>> = HashMap::new();
+
+    for total in totals
+        .iter()
+        .filter(|total| total.judge_id != judge_id && panel.contains(&total.judge_id))
+    {
+        others
+            .entry(total.candidate_id)
+            .or_default()
+            .push(total.total);
+    }
+
+    others
+        .into_iter()
+        .map(|(candidate_id, totals)| (candidate_id, mean(&totals)))
+        .collect()
+}
+
+// The judges the rest of the panel is made of, the same ones the tabulation counts in the
+// category
+pub fn category_panel(input: &TabulationInput, category: &Category) -> HashSet<uuid::Uuid> {
+    input
+        .judges
+        .iter()
+        .filter(|judge| assignment::on_panel(&input.assignments, judge, category))
+        .map(|judge| judge.id)
+        .collect()
+}
+
+// candidate_id -> final score as if the judge never scored
+pub fn final_scores_without(
+    input: &TabulationInput,
+    judge_id: uuid::Uuid,
+) -> HashMap<uuid::Uuid, f64> {
+    let mut input = input.clone();
+
+    for judge in input.judges.iter_mut().filter(|judge| judge.id == judge_id) {
+        judge.score_exclusion = true;
+    }
+
+    tabulation::tabulate(&input)
+        .into_iter()
+        .map(|result| {
+            (
+                result.candidate.id,
+                result.final_score.to_f64().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+pub async fn get_judge_analytics(
+    State(pool): State<PgPool>,
+    Query(param): Query<AnalyticsParam>,
+) -> Result<axum::Json<Vec<JudgeAnalytics>>, AppError> {
+    let threshold = param.threshold.unwrap_or(DEFAULT_OUTLIER_THRESHOLD);
+
+    let categories = sqlx::query_as::<_, Category>(
+        r#"
+        SELECT * FROM categories
+        WHERE (($1)::UUID IS NULL OR event_id = ($1)) AND (($2)::UUID IS NULL OR id = ($2))
+        ORDER BY name
+        "#,
+    )
+    .bind(param.event_id)
+    .bind(param.category_id)
+    .fetch_all(&pool)
+    .await?;
+
+    let judges: HashMap<uuid::Uuid, AnalyticsJudge> =
+        sqlx::query_as::<_, AnalyticsJudge>("SELECT id, name, score_exclusion FROM judges")
+            .fetch_all(&pool)
+            .await?
+            .into_iter()
+            .map(|judge| (judge.id, judge))
+            .collect();
+
+    let max_possible: HashMap<uuid::Uuid, f64> = sqlx::query_as::<_, (uuid::Uuid, f64)>(
+        "SELECT category_id, SUM(max_score)::FLOAT8 FROM criterias GROUP BY category_id",
+    )
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let input = tabulation::load_input(&pool).await?;
+    let totals = judge_totals(&input);
+
+    let candidate_numbers: HashMap<uuid::Uuid, i32> = input
+        .candidates
+        .iter()
+        .map(|candidate| (candidate.id, candidate.candidate_number))
+        .collect();
+
+    // judge_id -> final scores without them, tabulated once per judge
+    let mut final_scores: HashMap<uuid::Uuid, HashMap<uuid::Uuid, f64>> = HashMap::new();
+
+    let mut analytics = Vec::new();
+
+    for category in categories.iter() {
+        let category_totals: Vec<&JudgeTotal> = totals
+            .iter()
+            .filter(|total| total.category_id == category.id)
+            .collect();
+        let panel = category_panel(&input, category);
+
+        let mut judge_ids: Vec<uuid::Uuid> = category_totals.iter().map(|t| t.judge_id).collect();
+        judge_ids.sort();
+        judge_ids.dedup();
+
+        // judge_id -> the rest of the panel's mean for every candidate
+        let panel_means: HashMap<uuid::Uuid, HashMap<uuid::Uuid, f64>> = judge_ids
+            .iter()
+            .map(|judge_id| {
+                (
+                    *judge_id,
+                    panel_means_without(&category_totals, *judge_id, &panel),
+                )
+            })
+            .collect();
+
+        // Spread of every score's distance from the rest of the panel, used to flag outliers
+        let deviations: Vec<f64> = category_totals
+            .iter()
+            .filter_map(|total| {
+                panel_means[&total.judge_id]
+                    .get(&total.candidate_id)
+                    .map(|panel_mean| total.total - panel_mean)
+            })
+            .collect();
+        let deviation_std_dev = std_dev(&deviations);
+
+        for judge_id in judge_ids {
+            let Some(judge) = judges.get(&judge_id) else {
+                continue;
+            };
+
+            let panel_means = &panel_means[&judge_id];
+            let final_scores = final_scores
+                .entry(judge_id)
+                .or_insert_with(|| final_scores_without(&input, judge_id));
+
+            let judge_totals: Vec<&&JudgeTotal> = category_totals
+                .iter()
+                .filter(|total| total.judge_id == judge_id)
+                .collect();
+
+            let scores: Vec<f64> = judge_totals.iter().map(|total| total.total).collect();
+            let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let max_possible = max_possible.get(&category.id).copied().unwrap_or(0.0);
+
+            // Pairs of (judge's total, final score without the judge) for the candidates the
+            // judge scored
+            let (judge_scores, final_ranking): (Vec<f64>, Vec<f64>) = judge_totals
+                .iter()
+                .filter_map(|total| {
+                    final_scores
+                        .get(&total.candidate_id)
+                        .map(|final_score| (total.total, *final_score))
+                })
+                .unzip();
+
+            let outliers = judge_totals
+                .iter()
+                .filter_map(|total| {
+                    let panel_mean = *panel_means.get(&total.candidate_id)?;
+                    let deviation = total.total - panel_mean;
+
+                    if deviation_std_dev == 0.0 {
+                        return None;
+                    }
+
+                    let z_score = deviation / deviation_std_dev;
+
+                    (z_score.abs() > threshold).then(|| OutlierScore {
+                        candidate_id: total.candidate_id,
+                        candidate_number: candidate_numbers
+                            .get(&total.candidate_id)
+                            .copied()
+                            .unwrap_or_default(),
+                        score: total.total,
+                        panel_mean,
+                        deviation,
+                        z_score,
+                    })
+                })
+                .collect();
+
+            analytics.push(JudgeAnalytics {
+                judge_id,
+                judge_name: judge.name.clone(),
+                excluded: judge.score_exclusion,
+                category_id: category.id,
+                category_name: category.name.clone(),
+                candidates_scored: scores.len(),
+                mean: mean(&scores),
+                std_dev: std_dev(&scores),
+                min,
+                max,
+                max_possible,
+                range_usage: (max_possible > 0.0).then(|| (max - min) / max_possible),
+                spearman: spearman(&judge_scores, &final_ranking),
+                kendall: kendall_tau_b(&judge_scores, &final_ranking),
+                outliers,
+            });
+        }
+    }
+
+    Ok(axum::Json(analytics))
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Population standard deviation
+pub fn std_dev(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mean = mean(values);
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+
+    variance.sqrt()
+}
+
+// Rank 1 is the highest value, ties get the average of the ranks they span
+pub fn ranks<T: PartialOrd + Copy>(values: &[T]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| {
+        values[*b]
+            .partial_cmp(&values[*a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut i = 0;
+
+    while i < order.len() {
+        let mut j = i;
+
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+
+        for idx in order[i..=j].iter() {
+            ranks[*idx] = rank;
+        }
+
+        i = j + 1;
+    }
+
+    ranks
+}
+
+// Pearson correlation of the ranks, which handles ties properly
+pub fn spearman(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.len() < 2 {
+        return None;
+    }
+
+    let (rank_a, rank_b) = (ranks(a), ranks(b));
+    let (mean_a, mean_b) = (mean(&rank_a), mean(&rank_b));
+
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+
+    for (x, y) in rank_a.iter().zip(rank_b.iter()) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return None;
+    }
+
+    Some(covariance / (variance_a * variance_b).sqrt())
+}
+
+// Kendall's tau-b, which accounts for ties in either ranking
+pub fn kendall_tau_b(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.len() < 2 {
+        return None;
+    }
+
+    let mut concordant: f64 = 0.0;
+    let mut discordant: f64 = 0.0;
+    let mut ties_a: f64 = 0.0;
+    let mut ties_b: f64 = 0.0;
+
+    for i in 0..a.len() {
+        for j in (i + 1)..a.len() {
+            let da = a[i] - a[j];
+            let db = b[i] - b[j];
+
+            if da == 0.0 && db == 0.0 {
+                continue;
+            } else if da == 0.0 {
+                ties_a += 1.0;
+            } else if db == 0.0 {
+                ties_b += 1.0;
+            } else if da.signum() == db.signum() {
+                concordant += 1.0;
+            } else {
+                discordant += 1.0;
+            }
+        }
+    }
+
+    let denominator =
+        ((concordant + discordant + ties_a) * (concordant + discordant + ties_b)).sqrt();
+
+    if denominator == 0.0 {
+        return None;
+    }
+
+    Some((concordant - discordant) / denominator)
+}
@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{extract, http, response::Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+
+use super::category::Category;
+use super::tabulation::TabulationJudge;
+use super::{event, judge};
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Assignment {
+    judge_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+    // Overrides the judge's own weight in this category
+    weight: Option<Decimal>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAssignment {
+    category_id: uuid::Uuid,
+    weight: Option<Decimal>,
+}
+
+// Panels are part of the setup, they can't change once the event is live
+async fn ensure_setup(pool: &PgPool, judge_id: uuid::Uuid) -> Result<(), AppError> {
+    let event_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT event_id FROM judges WHERE id = ($1)")
+            .bind(judge_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("Judge not found"))?;
+
+    event::ensure_setup(pool, event_id).await
+}
+
+pub async fn create_assignment(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+    axum::Json(payload): axum::Json<CreateAssignment>,
+) -> Result<(http::StatusCode, axum::Json<Assignment>), AppError> {
+    ensure_setup(&pool, judge_id).await?;
+
+    if let Some(weight) = payload.weight {
+        judge::validate_weight(weight)?;
+    }
+
+    // The category has to be from the judge's own event
+    let assignment = sqlx::query_as::<_, Assignment>(
+        r#"
+        INSERT INTO judge_category_assignments (judge_id, category_id, weight)
+        SELECT j.id, c.id, ($3)
+        FROM judges j
+        JOIN categories c ON c.event_id = j.event_id
+        WHERE j.id = ($1) AND c.id = ($2)
+        ON CONFLICT (judge_id, category_id) DO UPDATE SET weight = EXCLUDED.weight
+        RETURNING *
+        "#,
+    )
+    .bind(judge_id)
+    .bind(payload.category_id)
+    .bind(payload.weight)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::validation("Category is not part of the judge's event"))?;
+
+    Ok((http::StatusCode::CREATED, axum::Json(assignment)))
+}
+
+pub async fn get_assignments(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+) -> Result<axum::Json<Vec<Assignment>>, AppError> {
+    let assignments = sqlx::query_as::<_, Assignment>(
+        "SELECT * FROM judge_category_assignments WHERE judge_id = ($1)",
+    )
+    .bind(judge_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(assignments))
+}
+
+// A category without assignments is scored by every judge of its event, so its last judge can't
+// be taken off the panel
+pub async fn delete_assignment(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path((judge_id, category_id)): extract::Path<(uuid::Uuid, uuid::Uuid)>,
+) -> Result<http::StatusCode, AppError> {
+    ensure_setup(&pool, judge_id).await?;
+
+    let mut txn = pool.begin().await?;
+
+    let judges = sqlx::query_scalar::<_, uuid::Uuid>(
+        "SELECT judge_id FROM judge_category_assignments WHERE category_id = ($1) FOR UPDATE",
+    )
+    .bind(category_id)
+    .fetch_all(&mut *txn)
+    .await?;
+
+    if !judges.contains(&judge_id) {
+        return Err(AppError::not_found("Judge is not assigned to the category"));
+    }
+
+    if judges.len() == 1 {
+        return Err(AppError::conflict(
+            "Judge is the last one on the category's panel, assign another judge first",
+        ));
+    }
+
+    sqlx::query(
+        "DELETE FROM judge_category_assignments WHERE judge_id = ($1) AND category_id = ($2)",
+    )
+    .bind(judge_id)
+    .bind(category_id)
+    .execute(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+// category_id -> judges assigned to it, categories without assignments are left out
+pub async fn fetch_assignments(
+    pool: &PgPool,
+) -> Result<HashMap<uuid::Uuid, HashSet<uuid::Uuid>>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, Assignment>("SELECT * FROM judge_category_assignments")
+        .fetch_all(pool)
+        .await?;
+
+    let mut assignments: HashMap<uuid::Uuid, HashSet<uuid::Uuid>> = HashMap::new();
+
+    for row in rows {
+        assignments
+            .entry(row.category_id)
+            .or_default()
+            .insert(row.judge_id);
+    }
+
+    Ok(assignments)
+}
+
+// Without any assignments a category is scored by every judge of its event, like before panels
+// existed
+pub fn is_assigned(
+    assignments: &HashMap<uuid::Uuid, HashSet<uuid::Uuid>>,
+    judge_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+) -> bool {
+    assignments
+        .get(&category_id)
+        .is_none_or(|judges| judges.contains(&judge_id))
+}
+
+pub async fn has_assignment(
+    pool: &PgPool,
+    judge_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+) -> Result<bool, AppError> {
+    let assigned = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM judges j
+            JOIN categories c ON c.event_id = j.event_id
+            WHERE j.id = ($1) AND c.id = ($2)
+                AND (
+                    NOT EXISTS (
+                        SELECT 1 FROM judge_category_assignments a WHERE a.category_id = c.id
+                    )
+                    OR EXISTS (
+                        SELECT 1 FROM judge_category_assignments a
+                        WHERE a.category_id = c.id AND a.judge_id = j.id
+                    )
+                )
+        )
+        "#,
+    )
+    .bind(judge_id)
+    .bind(category_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(assigned)
+}
+
+// Who scores a category: the judges of its event that are assigned to it and not excluded.
+// The tabulation and the exports both go through here so they can't disagree.
+pub fn on_panel(
+    assignments: &HashMap<uuid::Uuid, HashSet<uuid::Uuid>>,
+    judge: &TabulationJudge,
+    category: &Category,
+) -> bool {
+    judge.event_id == category.event_id
+        && !judge.score_exclusion
+        && is_assigned(assignments, judge.id, category.id)
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct PanelJudge {
+    #[sqlx(flatten)]
+    pub judge: TabulationJudge,
+    pub name: String,
+    // Of the judge in this category
+    pub weight: Decimal,
+}
+
+pub async fn fetch_panel(
+    pool: &PgPool,
+    category_id: uuid::Uuid,
+) -> Result<Vec<PanelJudge>, AppError> {
+    let category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = ($1)")
+        .bind(category_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Category not found"))?;
+
+    let judges = sqlx::query_as::<_, PanelJudge>(
+        r#"
+        SELECT j.id, j.event_id, j.score_exclusion, j.name, COALESCE(a.weight, j.weight) AS weight
+        FROM judges j
+        LEFT JOIN judge_category_assignments a ON a.judge_id = j.id AND a.category_id = ($1)
+        WHERE j.event_id = ($2)
+        ORDER BY j.name
+        "#,
+    )
+    .bind(category.id)
+    .bind(category.event_id)
+    .fetch_all(pool)
+    .await?;
+
+    let assignments = fetch_assignments(pool).await?;
+
+    Ok(judges
+        .into_iter()
+        .filter(|panel_judge| on_panel(&assignments, &panel_judge.judge, &category))
+        .collect())
+}
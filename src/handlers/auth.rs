@@ -33,10 +33,7 @@ pub async fn login(
                 .await
                 .map_err(|err| {
                     eprintln!("Failed to set is_active to TRUE");
-                    AppError::new(
-                        http::StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Failed to set is_active to TRUE: {}", err),
-                    )
+                    AppError::internal(format!("Failed to set is_active to TRUE: {}", err))
                 })?;
 
             println!("Welcome, {}!", judge.name);
@@ -44,13 +41,11 @@ pub async fn login(
 
             Ok(axum::Json(judge))
         }
+        Err(sqlx::Error::RowNotFound) => Err(AppError::unauthorized("Wrong username or password")),
         Err(err) => {
             eprintln!("Failed to login: {err:?}");
 
-            Err(AppError::new(
-                http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to login: {}", err),
-            ))
+            Err(err.into())
         }
     }
 }
@@ -78,10 +73,7 @@ pub async fn logout(
         Err(err) => {
             eprintln!("Failed to logout: {err:?}");
 
-            Err(AppError::new(
-                http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to logout: {}", err),
-            ))
+            Err(err.into())
         }
     }
 }
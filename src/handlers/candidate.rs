@@ -7,6 +7,8 @@ use sqlx::{FromRow, PgPool};
 
 use crate::error::AppError;
 
+use super::event;
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Candidate {
     pub id: uuid::Uuid,
@@ -36,6 +38,8 @@ pub async fn create_candidate(
     State(pool): State<PgPool>,
     axum::Json(payload): axum::Json<CreateCandidate>,
 ) -> Result<(http::StatusCode, axum::Json<Candidate>), AppError> {
+    event::ensure_category_setup(&pool, payload.category_id).await?;
+
     let candidate = sqlx::query_as::<_, Candidate>(
         r#"
         INSERT INTO candidates (first_name, middle_name, last_name, birthdate, gender, college, category_id) 
@@ -1,14 +1,19 @@
 use axum::{extract, http, response::Result};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, PgPool};
 
 use crate::error::AppError;
+use crate::realtime::Realtime;
 
-#[derive(Debug, Serialize, FromRow)]
+use super::event::{self, EventStatus};
+
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Category {
     pub id: uuid::Uuid,
     pub name: String,
-    pub weight: f32,
+    pub weight: Decimal,
     // Relationships
     pub event_id: uuid::Uuid,
 }
@@ -16,7 +21,55 @@ pub struct Category {
 #[derive(Debug, Deserialize)]
 pub struct CreateCategory {
     name: String,
-    weight: f32,
+    weight: Decimal,
+}
+
+// A category's weight is its share of the event, so it has to be above 0% and all of the event's
+// categories together can't go over 100%
+pub fn check_weight(weight: Decimal, other_weights: Decimal) -> Result<(), String> {
+    if weight <= Decimal::ZERO || weight > Decimal::ONE {
+        return Err("Weight must be greater than 0 and at most 1".to_string());
+    }
+
+    if weight + other_weights > Decimal::ONE {
+        return Err(format!(
+            "Category weights of the event would add up to {}%, only {}% is left",
+            ((weight + other_weights) * Decimal::ONE_HUNDRED).normalize(),
+            ((Decimal::ONE - other_weights) * Decimal::ONE_HUNDRED).normalize()
+        ));
+    }
+
+    Ok(())
+}
+
+// Has to run in the same transaction as the write to the category, the event's categories stay
+// locked until it commits so two writers can't both take the weight that is left
+async fn validate_weight(
+    conn: &mut PgConnection,
+    event_id: uuid::Uuid,
+    category_id: Option<uuid::Uuid>,
+    weight: Decimal,
+) -> Result<(), AppError> {
+    // Locking the event as well covers events that don't have any categories yet
+    sqlx::query("SELECT id FROM events WHERE id = ($1) FOR UPDATE")
+        .bind(event_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // Every other category of the event, the one being updated is left out
+    let other_weights: Vec<Decimal> = sqlx::query_scalar(
+        r#"
+        SELECT weight FROM categories
+        WHERE event_id = ($1) AND (($2)::UUID IS NULL OR id <> ($2))
+        FOR UPDATE
+        "#,
+    )
+    .bind(event_id)
+    .bind(category_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    check_weight(weight, other_weights.into_iter().sum()).map_err(AppError::validation)
 }
 
 pub async fn create_category(
@@ -24,6 +77,12 @@ pub async fn create_category(
     extract::Path(event_id): extract::Path<uuid::Uuid>,
     axum::Json(payload): axum::Json<CreateCategory>,
 ) -> Result<(http::StatusCode, axum::Json<Category>), AppError> {
+    event::ensure_setup(&pool, event_id).await?;
+
+    let mut txn = pool.begin().await?;
+
+    validate_weight(&mut txn, event_id, None, payload.weight).await?;
+
     let category = sqlx::query_as::<_, Category>(
         r#"
         INSERT INTO categories (name, weight, event_id) 
@@ -34,9 +93,11 @@ pub async fn create_category(
     .bind(&payload.name)
     .bind(&payload.weight)
     .bind(&event_id)
-    .fetch_one(&pool)
+    .fetch_one(&mut *txn)
     .await?;
 
+    txn.commit().await?;
+
     Ok((http::StatusCode::CREATED, axum::Json(category)))
 }
 
@@ -58,26 +119,80 @@ pub struct UpdateCategory {
     category_id: uuid::Uuid,
 }
 
+// Makes the category the one being scored, only the other categories of the same event are
+// deactivated
 pub async fn update_category(
     extract::State(pool): extract::State<PgPool>,
     extract::Path((event_id)): extract::Path<(uuid::Uuid)>,
     extract::Query((payload)): extract::Query<(UpdateCategory)>,
 ) -> Result<axum::Json<Category>, AppError> {
+    let status = event::fetch_status(&pool, event_id).await?;
+
+    if matches!(status, EventStatus::Closed | EventStatus::Archived) {
+        return Err(AppError::conflict(format!(
+            "Event is {}, categories can't be activated",
+            status.as_str()
+        )));
+    }
+
+    let mut txn = pool.begin().await?;
+
+    let category = sqlx::query_as::<_, Category>(
+        "UPDATE categories SET is_active = TRUE WHERE id = ($1) AND event_id = ($2) RETURNING *",
+    )
+    .bind(&payload.category_id)
+    .bind(&event_id)
+    .fetch_optional(&mut *txn)
+    .await?
+    .ok_or_else(|| AppError::not_found("Category not found in event"))?;
+
+    sqlx::query("UPDATE categories SET is_active = FALSE WHERE event_id = ($1) AND id <> ($2)")
+        .bind(&event_id)
+        .bind(&payload.category_id)
+        .execute(&mut *txn)
+        .await?;
+
+    txn.commit().await?;
+
+    Ok(axum::Json(category))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EditCategory {
+    name: Option<String>,
+    weight: Option<Decimal>,
+}
+
+pub async fn edit_category(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path((event_id, category_id)): extract::Path<(uuid::Uuid, uuid::Uuid)>,
+    axum::Json(payload): axum::Json<EditCategory>,
+) -> Result<axum::Json<Category>, AppError> {
+    event::ensure_setup(&pool, event_id).await?;
+
+    let mut txn = pool.begin().await?;
+
+    if let Some(weight) = payload.weight {
+        validate_weight(&mut txn, event_id, Some(category_id), weight).await?;
+    }
+
     let category = sqlx::query_as::<_, Category>(
         r#"
         UPDATE categories
-        SET is_active = CASE
-            WHEN id = ($1) and event_id = ($2) THEN TRUE
-            ELSE FALSE
-        END
-        RETURNING *;
+        SET name = COALESCE(($1), name), weight = COALESCE(($2), weight)
+        WHERE event_id = ($3) AND id = ($4)
+        RETURNING *
         "#,
     )
-    .bind(&payload.category_id)
-    .bind(&event_id)
-    .fetch_one(&pool)
+    .bind(&payload.name)
+    .bind(payload.weight)
+    .bind(event_id)
+    .bind(category_id)
+    .fetch_one(&mut *txn)
     .await?;
 
+    txn.commit().await?;
+
     Ok(axum::Json(category))
 }
 
@@ -95,3 +210,71 @@ pub async fn get_category(
 
     Ok(axum::Json(category))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct LockCategory {
+    locked: bool,
+}
+
+// Locking a category freezes its scores, after that judges have to file a correction request
+pub async fn lock_category(
+    extract::State(pool): extract::State<PgPool>,
+    extract::State(realtime): extract::State<Realtime>,
+    extract::Path((event_id, category_id)): extract::Path<(uuid::Uuid, uuid::Uuid)>,
+    axum::Json(payload): axum::Json<LockCategory>,
+) -> Result<axum::Json<Category>, AppError> {
+    event::ensure_changes(&pool, event_id).await?;
+
+    let category = sqlx::query_as::<_, Category>(
+        r#"
+        UPDATE categories
+        SET locked_at = CASE WHEN ($1) THEN COALESCE(locked_at, now()) ELSE NULL END
+        WHERE event_id = ($2) AND id = ($3)
+        RETURNING *
+        "#,
+    )
+    .bind(payload.locked)
+    .bind(event_id)
+    .bind(category_id)
+    .fetch_one(&pool)
+    .await?;
+
+    println!(
+        "Category {} {}\n",
+        category.name,
+        if payload.locked { "locked" } else { "unlocked" }
+    );
+
+    let notification = json!({
+        "type": "category_lock",
+        "event_id": category.event_id,
+        "category_id": category.id,
+        "locked": payload.locked,
+    });
+
+    realtime.publish(&notification.to_string());
+
+    Ok(axum::Json(category))
+}
+
+pub async fn is_locked(pool: &PgPool, category_id: uuid::Uuid) -> Result<bool, AppError> {
+    let locked = sqlx::query_scalar::<_, bool>(
+        "SELECT locked_at IS NOT NULL FROM categories WHERE id = ($1)",
+    )
+    .bind(category_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Category not found"))?;
+
+    Ok(locked)
+}
+
+pub async fn ensure_unlocked(pool: &PgPool, category_id: uuid::Uuid) -> Result<(), AppError> {
+    if is_locked(pool, category_id).await? {
+        return Err(AppError::conflict(
+            "Category is locked, file a correction request instead",
+        ));
+    }
+
+    Ok(())
+}
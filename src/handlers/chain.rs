@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use axum::extract::{Path, State};
+use axum::response::Result;
+use chrono::SubsecRound;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgConnection, PgPool};
+
+use crate::error::AppError;
+
+// prev_hash of the first entry of every event
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ChainEntry {
+    pub id: i64,
+    pub event_id: uuid::Uuid,
+    pub score_id: uuid::Uuid,
+    pub action: String,
+    pub score: Decimal,
+    pub max: Decimal,
+    pub candidate_id: uuid::Uuid,
+    pub criteria_id: uuid::Uuid,
+    pub category_id: uuid::Uuid,
+    pub judge_id: uuid::Uuid,
+    pub recorded_at: chrono::DateTime<chrono::Utc>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl ChainEntry {
+    // Hash of the entry's contents chained to the previous entry, the id is left out since
+    // it's only assigned by the database
+    pub fn compute_hash(&self) -> String {
+        let content = format!(
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            self.prev_hash,
+            self.event_id,
+            self.score_id,
+            self.action,
+            // 8.50 and 8.5 are the same score
+            self.score.normalize(),
+            self.max.normalize(),
+            self.candidate_id,
+            self.criteria_id,
+            self.category_id,
+            self.judge_id,
+            self.recorded_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        );
+
+        hex::encode(Sha256::digest(content.as_bytes()))
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct ChainedScore {
+    id: uuid::Uuid,
+    score: Decimal,
+    max: Decimal,
+    candidate_id: uuid::Uuid,
+    criteria_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+    judge_id: uuid::Uuid,
+    event_id: uuid::Uuid,
+}
+
+// Records the current state of a score at the end of its event's chain, has to run in the same
+// transaction as the write to scores so the two can't disagree
+pub async fn append(
+    conn: &mut PgConnection,
+    action: &str,
+    score_id: uuid::Uuid,
+) -> Result<ChainEntry, sqlx::Error> {
+    let score = sqlx::query_as::<_, ChainedScore>(
+        r#"
+        SELECT s.id, s.score, s.max, s.candidate_id, s.criteria_id, s.category_id, s.judge_id,
+            c.event_id
+        FROM scores s
+        JOIN categories c ON c.id = s.category_id
+        WHERE s.id = ($1)
+        "#,
+    )
+    .bind(score_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // Only one writer per event at a time, otherwise two entries could share a prev_hash
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext(($1)::TEXT))")
+        .bind(score.event_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let prev_hash = sqlx::query_scalar::<_, String>(
+        "SELECT hash FROM score_chain WHERE event_id = ($1) ORDER BY id DESC LIMIT 1",
+    )
+    .bind(score.event_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or_else(|| GENESIS_HASH.to_string());
+
+    let mut entry = ChainEntry {
+        id: 0,
+        event_id: score.event_id,
+        score_id: score.id,
+        action: action.to_string(),
+        score: score.score,
+        max: score.max,
+        candidate_id: score.candidate_id,
+        criteria_id: score.criteria_id,
+        category_id: score.category_id,
+        judge_id: score.judge_id,
+        // Postgres only keeps microseconds
+        recorded_at: chrono::Utc::now().trunc_subsecs(6),
+        prev_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry.compute_hash();
+
+    sqlx::query_as::<_, ChainEntry>(
+        r#"
+        INSERT INTO score_chain (event_id, score_id, action, score, max, candidate_id,
+            criteria_id, category_id, judge_id, recorded_at, prev_hash, hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING *
+        "#,
+    )
+    .bind(entry.event_id)
+    .bind(entry.score_id)
+    .bind(&entry.action)
+    .bind(entry.score)
+    .bind(entry.max)
+    .bind(entry.candidate_id)
+    .bind(entry.criteria_id)
+    .bind(entry.category_id)
+    .bind(entry.judge_id)
+    .bind(entry.recorded_at)
+    .bind(&entry.prev_hash)
+    .bind(&entry.hash)
+    .fetch_one(&mut *conn)
+    .await
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct ChainHead {
+    pub event_id: uuid::Uuid,
+    pub length: i64,
+    pub head_hash: String,
+}
+
+pub async fn fetch_heads(pool: &PgPool) -> Result<Vec<ChainHead>, sqlx::Error> {
+    sqlx::query_as::<_, ChainHead>(
+        r#"
+        SELECT DISTINCT ON (event_id) event_id,
+            COUNT(*) OVER (PARTITION BY event_id) AS length,
+            hash AS head_hash
+        FROM score_chain
+        ORDER BY event_id, id DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainBreak {
+    entry_id: i64,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainVerification {
+    pub valid: bool,
+    pub length: usize,
+    pub head_hash: String,
+    // The first entry where the chain stops adding up
+    pub broken_at: Option<ChainBreak>,
+    // Scores whose current value is not the last one recorded in the chain
+    pub mismatched_scores: Vec<uuid::Uuid>,
+}
+
+// Expects the entries of a single event in the order they were appended
+pub fn verify(entries: &[ChainEntry]) -> ChainVerification {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut broken_at = None;
+
+    for entry in entries.iter() {
+        let reason = if entry.prev_hash != prev_hash {
+            Some("prev_hash does not match the previous entry")
+        } else if entry.compute_hash() != entry.hash {
+            Some("hash does not match the entry's contents")
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            broken_at = Some(ChainBreak {
+                entry_id: entry.id,
+                reason: reason.to_string(),
+            });
+
+            break;
+        }
+
+        prev_hash = entry.hash.clone();
+    }
+
+    ChainVerification {
+        valid: broken_at.is_none(),
+        length: entries.len(),
+        head_hash: entries
+            .last()
+            .map(|entry| entry.hash.clone())
+            .unwrap_or_else(|| GENESIS_HASH.to_string()),
+        broken_at,
+        mismatched_scores: Vec::new(),
+    }
+}
+
+// Walks the whole chain of an event and compares it against the scores as they are now
+pub async fn verify_chain(
+    State(pool): State<PgPool>,
+    Path(event_id): Path<uuid::Uuid>,
+) -> Result<axum::Json<ChainVerification>, AppError> {
+    let entries = sqlx::query_as::<_, ChainEntry>(
+        "SELECT * FROM score_chain WHERE event_id = ($1) ORDER BY id",
+    )
+    .bind(event_id)
+    .fetch_all(&pool)
+    .await?;
+
+    let mut verification = verify(&entries);
+
+    let latest: HashMap<uuid::Uuid, &ChainEntry> = entries
+        .iter()
+        .map(|entry| (entry.score_id, entry))
+        .collect();
+
+    let scores = sqlx::query_as::<_, ChainedScore>(
+        r#"
+        SELECT s.id, s.score, s.max, s.candidate_id, s.criteria_id, s.category_id, s.judge_id,
+            c.event_id
+        FROM scores s
+        JOIN categories c ON c.id = s.category_id
+        WHERE c.event_id = ($1)
+        "#,
+    )
+    .bind(event_id)
+    .fetch_all(&pool)
+    .await?;
+
+    verification.mismatched_scores = scores
+        .iter()
+        .filter(|score| match latest.get(&score.id) {
+            Some(entry) => {
+                entry.score != score.score
+                    || entry.max != score.max
+                    || entry.candidate_id != score.candidate_id
+                    || entry.criteria_id != score.criteria_id
+                    || entry.judge_id != score.judge_id
+            }
+            // Written without going through the API
+            None => true,
+        })
+        .map(|score| score.id)
+        .collect();
+
+    verification.valid = verification.valid && verification.mismatched_scores.is_empty();
+
+    Ok(axum::Json(verification))
+}
@@ -14,15 +14,9 @@ pub struct College {
 pub async fn get_colleges(
     extract::State(pool): extract::State<PgPool>,
 ) -> Result<axum::Json<Vec<College>>, AppError> {
-    let res = sqlx::query_as::<_, College>("SELECT * FROM college")
+    let colleges = sqlx::query_as::<_, College>("SELECT * FROM college")
         .fetch_all(&pool)
-        .await;
+        .await?;
 
-    match res {
-        Ok(category) => Ok(axum::Json(category)),
-        Err(err) => Err(AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to get colleges: {}", err),
-        )),
-    }
+    Ok(axum::Json(colleges))
 }
@@ -0,0 +1,263 @@
+use axum::{extract, http, response::Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+use crate::realtime::Realtime;
+
+use super::{category, chain, event, score};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "correction_status", rename_all = "snake_case")]
+pub enum CorrectionStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Correction {
+    id: uuid::Uuid,
+    score_id: uuid::Uuid,
+    judge_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+    previous_score: Decimal,
+    requested_score: Decimal,
+    reason: String,
+    status: CorrectionStatus,
+    reviewed_by: Option<String>,
+    review_note: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    reviewed_at: Option<chrono::DateTime<chrono::Utc>>,
+    // Of the score when the correction was filed
+    score_version: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCorrection {
+    score_id: uuid::Uuid,
+    judge_id: uuid::Uuid,
+    score: Decimal,
+    reason: String,
+}
+
+#[derive(Debug, FromRow)]
+struct CorrectedScore {
+    judge_id: uuid::Uuid,
+    criteria_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+    score: Decimal,
+    version: i32,
+}
+
+// Filed by a judge for one of their own scores in a locked category
+pub async fn create_correction(
+    extract::State(pool): extract::State<PgPool>,
+    extract::State(realtime): extract::State<Realtime>,
+    axum::Json(payload): axum::Json<CreateCorrection>,
+) -> Result<(http::StatusCode, axum::Json<Correction>), AppError> {
+    if payload.reason.trim().is_empty() {
+        return Err(AppError::validation(
+            "A reason is required to request a correction",
+        ));
+    }
+
+    let current = sqlx::query_as::<_, CorrectedScore>(
+        "SELECT judge_id, criteria_id, category_id, score, version FROM scores WHERE id = ($1)",
+    )
+    .bind(payload.score_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Score not found"))?;
+
+    if current.judge_id != payload.judge_id {
+        return Err(AppError::forbidden(
+            "Judges can only request corrections of their own scores",
+        ));
+    }
+
+    if !category::is_locked(&pool, current.category_id).await? {
+        return Err(AppError::conflict(
+            "Category is not locked, update the score directly",
+        ));
+    }
+
+    event::ensure_scoring(&pool, current.category_id).await?;
+    score::validate_score(&pool, current.criteria_id, payload.score).await?;
+
+    let correction = sqlx::query_as::<_, Correction>(
+        r#"
+        INSERT INTO score_corrections (score_id, judge_id, category_id, previous_score,
+            requested_score, reason, score_version)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (score_id) WHERE status = 'pending' DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(payload.score_id)
+    .bind(payload.judge_id)
+    .bind(current.category_id)
+    .bind(current.score)
+    .bind(payload.score)
+    .bind(payload.reason.trim())
+    .bind(current.version)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::conflict("A correction of this score is already pending"))?;
+
+    let event_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT event_id FROM categories WHERE id = ($1)")
+            .bind(correction.category_id)
+            .fetch_one(&pool)
+            .await?;
+
+    // Only tabulators get pending requests, judge tablets are left out by the message type
+    let notification = json!({
+        "type": "score_correction",
+        "event_id": event_id,
+        "category_id": correction.category_id,
+        "judge_id": correction.judge_id,
+        "correction": correction,
+    });
+
+    realtime.publish(&notification.to_string());
+
+    Ok((http::StatusCode::CREATED, axum::Json(correction)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CorrectionParam {
+    status: Option<CorrectionStatus>,
+    judge_id: Option<uuid::Uuid>,
+}
+
+pub async fn get_corrections(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Query(param): extract::Query<CorrectionParam>,
+) -> Result<axum::Json<Vec<Correction>>, AppError> {
+    let corrections = sqlx::query_as::<_, Correction>(
+        r#"
+        SELECT * FROM score_corrections
+        WHERE (($1)::correction_status IS NULL OR status = ($1))
+            AND (($2)::UUID IS NULL OR judge_id = ($2))
+        ORDER BY created_at
+        "#,
+    )
+    .bind(param.status)
+    .bind(param.judge_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(corrections))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewCorrection {
+    approved: bool,
+    reviewed_by: String,
+    note: Option<String>,
+}
+
+// Approved corrections are applied to the score and recorded in the score chain
+pub async fn review_correction(
+    extract::State(pool): extract::State<PgPool>,
+    extract::State(realtime): extract::State<Realtime>,
+    extract::Path(correction_id): extract::Path<uuid::Uuid>,
+    axum::Json(payload): axum::Json<ReviewCorrection>,
+) -> Result<axum::Json<Correction>, AppError> {
+    if payload.reviewed_by.trim().is_empty() {
+        return Err(AppError::validation(
+            "A correction needs to say who reviewed it",
+        ));
+    }
+
+    let mut txn = pool.begin().await?;
+
+    let correction = sqlx::query_as::<_, Correction>(
+        "SELECT * FROM score_corrections WHERE id = ($1) FOR UPDATE",
+    )
+    .bind(correction_id)
+    .fetch_optional(&mut *txn)
+    .await?
+    .ok_or_else(|| AppError::not_found("Correction not found"))?;
+
+    if correction.status != CorrectionStatus::Pending {
+        return Err(AppError::conflict("Correction has already been reviewed"));
+    }
+
+    let status = if payload.approved {
+        event::ensure_scoring(&pool, correction.category_id).await?;
+
+        let current =
+            sqlx::query_as::<_, score::Score>("SELECT * FROM scores WHERE id = ($1) FOR UPDATE")
+                .bind(correction.score_id)
+                .fetch_one(&mut *txn)
+                .await?;
+
+        // The score was changed after the correction was filed
+        if let Some(version) = correction.score_version {
+            score::check_version(version, &current)?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE scores SET score = ($1), time_of_scoring = now(), version = version + 1
+            WHERE id = ($2)
+            "#,
+        )
+        .bind(correction.requested_score)
+        .bind(correction.score_id)
+        .execute(&mut *txn)
+        .await?;
+
+        chain::append(&mut txn, "correction", correction.score_id).await?;
+
+        CorrectionStatus::Approved
+    } else {
+        CorrectionStatus::Rejected
+    };
+
+    let correction = sqlx::query_as::<_, Correction>(
+        r#"
+        UPDATE score_corrections
+        SET status = ($1), reviewed_by = ($2), review_note = ($3), reviewed_at = now()
+        WHERE id = ($4)
+        RETURNING *
+        "#,
+    )
+    .bind(status)
+    .bind(payload.reviewed_by.trim())
+    .bind(&payload.note)
+    .bind(correction_id)
+    .fetch_one(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    println!(
+        "Correction of score {} {:?} by {}\n",
+        correction.score_id, correction.status, payload.reviewed_by
+    );
+
+    let event_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT event_id FROM categories WHERE id = ($1)")
+            .bind(correction.category_id)
+            .fetch_one(&pool)
+            .await?;
+
+    // The judge that filed it gets to know the outcome too
+    let notification = json!({
+        "type": "score_correction_reviewed",
+        "event_id": event_id,
+        "category_id": correction.category_id,
+        "judge_id": correction.judge_id,
+        "correction": correction,
+    });
+
+    realtime.publish(&notification.to_string());
+
+    Ok(axum::Json(correction))
+}
@@ -1,15 +1,20 @@
 use axum::response::Result;
 use axum::{extract, http};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 
 use crate::error::AppError;
 
+use super::event;
+
 #[derive(Debug, Serialize, FromRow)]
 pub struct Criteria {
     id: uuid::Uuid,
     name: String,
-    max_score: i32,
+    max_score: Decimal,
+    score_step: Decimal,
+    weight: Option<Decimal>,
     // Relationships
     category_id: uuid::Uuid,
 }
@@ -17,54 +22,88 @@ pub struct Criteria {
 #[derive(Debug, Deserialize)]
 pub struct CreateCriteria {
     name: String,
-    max_score: i32,
+    max_score: Decimal,
+    // Defaults to whole points
+    score_step: Option<Decimal>,
+    // Leave empty to weight the criteria by its max score
+    weight: Option<Decimal>,
 }
 
 // POST
 pub async fn create_criteria(
     extract::State(pool): extract::State<PgPool>,
-    extract::Path((_event_id, category_id)): extract::Path<(uuid::Uuid, uuid::Uuid)>,
+    extract::Path((event_id, category_id)): extract::Path<(uuid::Uuid, uuid::Uuid)>,
     axum::Json(payload): axum::Json<CreateCriteria>,
 ) -> Result<(http::StatusCode, axum::Json<Criteria>), AppError> {
-    let res = sqlx::query_as::<_, Criteria>(
+    let category_event_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT event_id FROM categories WHERE id = ($1)")
+            .bind(category_id)
+            .fetch_optional(&pool)
+            .await?;
+
+    if category_event_id != Some(event_id) {
+        return Err(AppError::not_found("Category not found in event"));
+    }
+
+    event::ensure_setup(&pool, event_id).await?;
+
+    let score_step = payload.score_step.unwrap_or(Decimal::ONE);
+
+    if payload.max_score <= Decimal::ZERO
+        || score_step <= Decimal::ZERO
+        || !(payload.max_score % score_step).is_zero()
+    {
+        return Err(AppError::validation(
+            "max_score and score_step must be positive and max_score must be a multiple of score_step",
+        ));
+    }
+
+    // Both are stored with 2 decimal places, anything finer would be rounded off on insert and
+    // the step the judges see would no longer divide max_score
+    if payload.max_score.normalize().scale() > 2 || score_step.normalize().scale() > 2 {
+        return Err(AppError::validation(
+            "max_score and score_step can have at most 2 decimal places",
+        ));
+    }
+
+    if payload
+        .weight
+        .is_some_and(|weight| weight <= Decimal::ZERO || weight > Decimal::ONE)
+    {
+        return Err(AppError::validation(
+            "weight must be greater than 0 and at most 1",
+        ));
+    }
+
+    let criteria = sqlx::query_as::<_, Criteria>(
         r#"
-        INSERT INTO criterias (name, description, max_score, category_id) 
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO criterias (name, max_score, score_step, weight, category_id) 
+        VALUES ($1, $2, $3, $4, $5)
         RETURNING *
         "#,
     )
     .bind(&payload.name)
     .bind(&payload.max_score)
+    .bind(&score_step)
+    .bind(&payload.weight)
     .bind(&category_id)
     .fetch_one(&pool)
-    .await;
-
-    match res {
-        Ok(criteria) => Ok((http::StatusCode::CREATED, axum::Json(criteria))),
-        Err(err) => Err(AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to create criteria: {}", err),
-        )),
-    }
+    .await?;
+
+    Ok((http::StatusCode::CREATED, axum::Json(criteria)))
 }
 
 pub async fn get_criterias(
     extract::State(pool): extract::State<PgPool>,
     extract::Path((_event_id, category_id)): extract::Path<(uuid::Uuid, uuid::Uuid)>,
-) -> Result<axum::Json<Vec<Criteria>>, http::StatusCode> {
-    let res = sqlx::query_as::<_, Criteria>("SELECT * FROM criterias WHERE category_id = ($1)")
-        .bind(&category_id)
-        .fetch_all(&pool)
-        .await;
-
-    match res {
-        Ok(criterias) => Ok(axum::Json(criterias)),
-        Err(err) => {
-            eprintln!("Failed to get criterias: {err:?}");
-
-            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+) -> Result<axum::Json<Vec<Criteria>>, AppError> {
+    let criterias =
+        sqlx::query_as::<_, Criteria>("SELECT * FROM criterias WHERE category_id = ($1)")
+            .bind(&category_id)
+            .fetch_all(&pool)
+            .await?;
+
+    Ok(axum::Json(criterias))
 }
 
 pub async fn get_criteria(
@@ -74,21 +113,15 @@ pub async fn get_criteria(
         uuid::Uuid,
         uuid::Uuid,
     )>,
-) -> Result<axum::Json<Criteria>, http::StatusCode> {
-    let res = sqlx::query_as::<_, Criteria>(
+) -> Result<axum::Json<Criteria>, AppError> {
+    let criteria = sqlx::query_as::<_, Criteria>(
         "SELECT * FROM criterias WHERE category_id = ($1) AND id = ($2)",
     )
     .bind(&category_id)
     .bind(&criteria_id)
-    .fetch_one(&pool)
-    .await;
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Criteria not found"))?;
 
-    match res {
-        Ok(criteria) => Ok(axum::Json(criteria)),
-        Err(err) => {
-            eprintln!("Failed to get criteria: {err:?}");
-
-            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(axum::Json(criteria))
 }
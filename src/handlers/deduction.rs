@@ -0,0 +1,186 @@
+use axum::{extract, http, response::Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+
+use super::event;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Deduction {
+    id: uuid::Uuid,
+    candidate_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+    // Taken off the candidate's score in the category, out of 100
+    points: Decimal,
+    reason: String,
+    approved_by: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDeduction {
+    candidate_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+    points: Decimal,
+    reason: String,
+    approved_by: String,
+}
+
+pub async fn create_deduction(
+    extract::State(pool): extract::State<PgPool>,
+    axum::Json(payload): axum::Json<CreateDeduction>,
+) -> Result<(http::StatusCode, axum::Json<Deduction>), AppError> {
+    if payload.points <= Decimal::ZERO || payload.points > Decimal::ONE_HUNDRED {
+        return Err(AppError::validation(
+            "Points must be greater than 0 and at most 100",
+        ));
+    }
+
+    if payload.reason.trim().is_empty() || payload.approved_by.trim().is_empty() {
+        return Err(AppError::validation(
+            "A deduction needs a reason and who approved it",
+        ));
+    }
+
+    event::ensure_category_changes(&pool, payload.category_id).await?;
+
+    let deduction = sqlx::query_as::<_, Deduction>(
+        r#"
+        INSERT INTO deductions (candidate_id, category_id, points, reason, approved_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(payload.candidate_id)
+    .bind(payload.category_id)
+    .bind(payload.points)
+    .bind(payload.reason.trim())
+    .bind(payload.approved_by.trim())
+    .fetch_one(&pool)
+    .await?;
+
+    Ok((http::StatusCode::CREATED, axum::Json(deduction)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeductionParam {
+    candidate_id: Option<uuid::Uuid>,
+    category_id: Option<uuid::Uuid>,
+}
+
+pub async fn get_deductions(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Query(param): extract::Query<DeductionParam>,
+) -> Result<axum::Json<Vec<Deduction>>, AppError> {
+    let deductions = sqlx::query_as::<_, Deduction>(
+        r#"
+        SELECT * FROM deductions
+        WHERE (($1)::UUID IS NULL OR candidate_id = ($1))
+            AND (($2)::UUID IS NULL OR category_id = ($2))
+        ORDER BY created_at
+        "#,
+    )
+    .bind(param.candidate_id)
+    .bind(param.category_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(deductions))
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DeductionLog {
+    id: uuid::Uuid,
+    deduction_id: uuid::Uuid,
+    candidate_id: uuid::Uuid,
+    category_id: uuid::Uuid,
+    points: Decimal,
+    reason: String,
+    approved_by: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    deleted_by: String,
+    delete_reason: String,
+    deleted_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteDeduction {
+    deleted_by: String,
+    reason: String,
+}
+
+// The deduction is kept in the logs as it was, so taking it back can still be traced
+pub async fn delete_deduction(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path(deduction_id): extract::Path<uuid::Uuid>,
+    axum::Json(payload): axum::Json<DeleteDeduction>,
+) -> Result<http::StatusCode, AppError> {
+    if payload.reason.trim().is_empty() || payload.deleted_by.trim().is_empty() {
+        return Err(AppError::validation(
+            "Deleting a deduction needs a reason and who deleted it",
+        ));
+    }
+
+    let category_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT category_id FROM deductions WHERE id = ($1)")
+            .bind(deduction_id)
+            .fetch_optional(&pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("Deduction not found"))?;
+
+    event::ensure_category_changes(&pool, category_id).await?;
+
+    let mut txn = pool.begin().await?;
+
+    let deduction =
+        sqlx::query_as::<_, Deduction>("DELETE FROM deductions WHERE id = ($1) RETURNING *")
+            .bind(deduction_id)
+            .fetch_optional(&mut *txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Deduction not found"))?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO deduction_logs (deduction_id, candidate_id, category_id, points, reason,
+            approved_by, created_at, deleted_by, delete_reason)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(deduction.id)
+    .bind(deduction.candidate_id)
+    .bind(deduction.category_id)
+    .bind(deduction.points)
+    .bind(&deduction.reason)
+    .bind(&deduction.approved_by)
+    .bind(deduction.created_at)
+    .bind(payload.deleted_by.trim())
+    .bind(payload.reason.trim())
+    .execute(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
+
+pub async fn get_deduction_logs(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Query(param): extract::Query<DeductionParam>,
+) -> Result<axum::Json<Vec<DeductionLog>>, AppError> {
+    let logs = sqlx::query_as::<_, DeductionLog>(
+        r#"
+        SELECT * FROM deduction_logs
+        WHERE (($1)::UUID IS NULL OR candidate_id = ($1))
+            AND (($2)::UUID IS NULL OR category_id = ($2))
+        ORDER BY deleted_at DESC
+        "#,
+    )
+    .bind(param.candidate_id)
+    .bind(param.category_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(logs))
+}
@@ -2,13 +2,75 @@ use axum::extract::Path;
 use axum::response::Result;
 use axum::{extract::State, http};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::{FromRow, PgPool};
 
+use crate::error::AppError;
+use crate::realtime::Realtime;
+
+use super::readiness;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "event_status", rename_all = "snake_case")]
+pub enum EventStatus {
+    Draft,
+    Setup,
+    Live,
+    Closed,
+    Archived,
+}
+
+impl EventStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventStatus::Draft => "draft",
+            EventStatus::Setup => "setup",
+            EventStatus::Live => "live",
+            EventStatus::Closed => "closed",
+            EventStatus::Archived => "archived",
+        }
+    }
+
+    // An event can step back to fix a mistake, e.g. reopening scoring after closing too early,
+    // but once archived it stays that way
+    pub fn can_transition_to(self, next: EventStatus) -> bool {
+        use EventStatus::*;
+
+        matches!(
+            (self, next),
+            (Draft, Setup)
+                | (Setup, Draft)
+                | (Setup, Live)
+                | (Live, Closed)
+                | (Closed, Live)
+                | (Closed, Archived)
+        )
+    }
+
+    // Categories, criterias and judges can only change before the event goes live
+    pub fn allows_setup(self) -> bool {
+        matches!(self, EventStatus::Draft | EventStatus::Setup)
+    }
+
+    pub fn allows_scoring(self) -> bool {
+        self == EventStatus::Live
+    }
+
+    // Deductions, exclusions and category locks change the results, so a closed event has to be
+    // reopened first and an archived one can't be touched at all
+    pub fn allows_changes(self) -> bool {
+        !matches!(self, EventStatus::Closed | EventStatus::Archived)
+    }
+}
+
 #[derive(Debug, Serialize, FromRow)]
 pub struct Event {
     id: uuid::Uuid,
     name: String,
+    // Same as status being live, kept for older clients
     active_event: bool,
+    status: EventStatus,
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,54 +81,195 @@ pub struct CreateEvent {
 pub async fn create_event(
     State(pool): State<PgPool>,
     axum::Json(payload): axum::Json<CreateEvent>,
-) -> Result<(http::StatusCode, axum::Json<Event>), http::StatusCode> {
-    let res = sqlx::query_as::<_, Event>("INSERT INTO events (name) VALUES ($1) RETURNING *")
-        .bind(&payload.name)
-        .fetch_one(&pool)
-        .await;
+) -> Result<(http::StatusCode, axum::Json<Event>), AppError> {
+    if payload.name.trim().is_empty() {
+        return Err(AppError::validation("Event name can't be empty"));
+    }
 
-    match res {
-        Ok(event) => Ok((http::StatusCode::CREATED, axum::Json(event))),
-        Err(err) => {
-            eprintln!("Failed to create event: {err:?}");
+    let event = sqlx::query_as::<_, Event>("INSERT INTO events (name) VALUES ($1) RETURNING *")
+        .bind(payload.name.trim())
+        .fetch_one(&pool)
+        .await?;
 
-            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok((http::StatusCode::CREATED, axum::Json(event)))
 }
 
-pub async fn get_events(
-    State(pool): State<PgPool>,
-) -> Result<axum::Json<Vec<Event>>, http::StatusCode> {
-    let res = sqlx::query_as::<_, Event>("SELECT * FROM events")
+pub async fn get_events(State(pool): State<PgPool>) -> Result<axum::Json<Vec<Event>>, AppError> {
+    let events = sqlx::query_as::<_, Event>("SELECT * FROM events")
         .fetch_all(&pool)
-        .await;
+        .await?;
 
-    match res {
-        Ok(events) => Ok(axum::Json(events)),
-        Err(err) => {
-            eprintln!("Failed to get events: {err:?}");
-
-            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(axum::Json(events))
 }
 
 pub async fn get_event(
     State(pool): State<PgPool>,
     Path(id): Path<uuid::Uuid>,
-) -> Result<axum::Json<Event>, http::StatusCode> {
-    let res = sqlx::query_as::<_, Event>("SELECT * FROM events WHERE id = ($1)")
+) -> Result<axum::Json<Event>, AppError> {
+    let event = sqlx::query_as::<_, Event>("SELECT * FROM events WHERE id = ($1)")
         .bind(&id)
-        .fetch_one(&pool)
-        .await;
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Event not found"))?;
+
+    Ok(axum::Json(event))
+}
+
+pub async fn fetch_status(pool: &PgPool, event_id: uuid::Uuid) -> Result<EventStatus, AppError> {
+    sqlx::query_scalar::<_, EventStatus>("SELECT status FROM events WHERE id = ($1)")
+        .bind(event_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Event not found"))
+}
+
+pub async fn ensure_setup(pool: &PgPool, event_id: uuid::Uuid) -> Result<(), AppError> {
+    let status = fetch_status(pool, event_id).await?;
+
+    if !status.allows_setup() {
+        return Err(AppError::conflict(format!(
+            "Event is {}, its setup can no longer be changed",
+            status.as_str()
+        )));
+    }
+
+    Ok(())
+}
+
+async fn fetch_category_status(
+    pool: &PgPool,
+    category_id: uuid::Uuid,
+) -> Result<EventStatus, AppError> {
+    sqlx::query_scalar::<_, EventStatus>(
+        r#"
+        SELECT e.status
+        FROM categories c
+        JOIN events e ON e.id = c.event_id
+        WHERE c.id = ($1)
+        "#,
+    )
+    .bind(category_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Category not found"))
+}
+
+// Same as ensure_setup, for things that only know their category
+pub async fn ensure_category_setup(pool: &PgPool, category_id: uuid::Uuid) -> Result<(), AppError> {
+    let status = fetch_category_status(pool, category_id).await?;
+
+    if !status.allows_setup() {
+        return Err(AppError::conflict(format!(
+            "Event is {}, its setup can no longer be changed",
+            status.as_str()
+        )));
+    }
+
+    Ok(())
+}
+
+pub async fn ensure_scoring(pool: &PgPool, category_id: uuid::Uuid) -> Result<(), AppError> {
+    let status = fetch_category_status(pool, category_id).await?;
 
-    match res {
-        Ok(event) => Ok(axum::Json(event)),
-        Err(err) => {
-            eprintln!("Failed to get event: {err:?}");
+    if !status.allows_scoring() {
+        return Err(AppError::conflict(format!(
+            "Event is {}, scores can't be submitted",
+            status.as_str()
+        )));
+    }
+
+    Ok(())
+}
+
+fn check_changes(status: EventStatus) -> Result<(), AppError> {
+    if !status.allows_changes() {
+        return Err(AppError::conflict(format!(
+            "Event is {}, its results can't be changed",
+            status.as_str()
+        )));
+    }
+
+    Ok(())
+}
+
+pub async fn ensure_changes(pool: &PgPool, event_id: uuid::Uuid) -> Result<(), AppError> {
+    check_changes(fetch_status(pool, event_id).await?)
+}
 
-            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
+pub async fn ensure_category_changes(
+    pool: &PgPool,
+    category_id: uuid::Uuid,
+) -> Result<(), AppError> {
+    check_changes(fetch_category_status(pool, category_id).await?)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateEventStatus {
+    status: EventStatus,
+}
+
+pub async fn update_event_status(
+    State(pool): State<PgPool>,
+    State(realtime): State<Realtime>,
+    Path(event_id): Path<uuid::Uuid>,
+    axum::Json(payload): axum::Json<UpdateEventStatus>,
+) -> Result<axum::Json<Event>, AppError> {
+    let mut txn = pool.begin().await?;
+
+    let status = sqlx::query_scalar::<_, EventStatus>(
+        "SELECT status FROM events WHERE id = ($1) FOR UPDATE",
+    )
+    .bind(event_id)
+    .fetch_optional(&mut *txn)
+    .await?
+    .ok_or_else(|| AppError::not_found("Event not found"))?;
+
+    if !status.can_transition_to(payload.status) {
+        return Err(AppError::conflict(format!(
+            "Event can't go from {} to {}",
+            status.as_str(),
+            payload.status.as_str()
+        )));
+    }
+
+    // Reopening a closed event doesn't need the check again
+    if status == EventStatus::Setup && payload.status == EventStatus::Live {
+        let readiness = readiness::fetch_readiness(&pool, event_id).await?;
+
+        if !readiness.ready {
+            return Err(AppError::validation("Event is not ready to go live")
+                .with_details(json!(readiness)));
         }
     }
+
+    let event = sqlx::query_as::<_, Event>(
+        r#"
+        UPDATE events SET status = ($1), active_event = ($1 = 'live')
+        WHERE id = ($2)
+        RETURNING *
+        "#,
+    )
+    .bind(payload.status)
+    .bind(event_id)
+    .fetch_one(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    println!(
+        "Event {} went from {} to {}\n",
+        event_id,
+        status.as_str(),
+        payload.status.as_str()
+    );
+
+    let notification = json!({
+        "type": "event_status",
+        "event_id": event_id,
+        "status": payload.status,
+    });
+
+    realtime.publish(&notification.to_string());
+
+    Ok(axum::Json(event))
 }
@@ -1,9 +1,14 @@
 use axum::response::Result;
 use axum::{extract, http};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::{FromRow, PgPool};
 
 use crate::error::AppError;
+use crate::realtime::Realtime;
+
+use super::event;
 
 #[derive(Debug, Serialize, FromRow)]
 pub struct Judge {
@@ -12,6 +17,9 @@ pub struct Judge {
     pub username: String,
     pub password: String,
     pub is_active: bool,
+    pub score_exclusion: bool,
+    // Relative to the rest of the panel, 1 unless e.g. head judge
+    pub weight: Decimal,
     // Relationships
     pub event_id: uuid::Uuid,
 }
@@ -22,17 +30,34 @@ pub struct CreateJudge {
     username: String,
     password: String,
     is_active: bool,
+    weight: Option<Decimal>,
     event_id: uuid::Uuid,
 }
 
+pub fn validate_weight(weight: Decimal) -> Result<(), AppError> {
+    if weight <= Decimal::ZERO || weight > Decimal::ONE_HUNDRED {
+        return Err(AppError::validation(
+            "Judge weight must be greater than 0 and at most 100",
+        ));
+    }
+
+    Ok(())
+}
+
 pub async fn create_judge(
     extract::State(pool): extract::State<PgPool>,
     axum::Json(payload): axum::Json<CreateJudge>,
 ) -> Result<(http::StatusCode, axum::Json<Judge>), AppError> {
-    let res = sqlx::query_as::<_, Judge>(
+    event::ensure_setup(&pool, payload.event_id).await?;
+
+    let weight = payload.weight.unwrap_or(Decimal::ONE);
+
+    validate_weight(weight)?;
+
+    let judge = sqlx::query_as::<_, Judge>(
         r#"
-        INSERT INTO judges (name, username, password, is_active, event_id) 
-        VALUES ($1, $2, $3, $4, $5) 
+        INSERT INTO judges (name, username, password, is_active, weight, event_id) 
+        VALUES ($1, $2, $3, $4, $5, $6) 
         RETURNING *
         "#,
     )
@@ -40,49 +65,158 @@ pub async fn create_judge(
     .bind(&payload.username)
     .bind(&payload.password)
     .bind(&payload.is_active)
+    .bind(weight)
     .bind(&payload.event_id)
     .fetch_one(&pool)
-    .await;
-
-    match res {
-        Ok(judge) => Ok((http::StatusCode::CREATED, axum::Json(judge))),
-        Err(err) => Err(AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to create judge: {}", err),
-        )),
-    }
+    .await?;
+
+    Ok((http::StatusCode::CREATED, axum::Json(judge)))
 }
 
 pub async fn get_judges(
     extract::State(pool): extract::State<PgPool>,
 ) -> Result<axum::Json<Vec<Judge>>, AppError> {
-    let res = sqlx::query_as::<_, Judge>("SELECT * FROM judges")
+    let judges = sqlx::query_as::<_, Judge>("SELECT * FROM judges")
         .fetch_all(&pool)
-        .await;
-
-    match res {
-        Ok(judges) => Ok(axum::Json(judges)),
-        Err(err) => Err(AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to get judges: {}", err),
-        )),
-    }
+        .await?;
+
+    Ok(axum::Json(judges))
 }
 
 pub async fn get_judge(
     extract::State(pool): extract::State<PgPool>,
     extract::Path(judge_id): extract::Path<uuid::Uuid>,
 ) -> Result<axum::Json<Judge>, AppError> {
-    let res = sqlx::query_as::<_, Judge>("SELECT * FROM judges WHERE id = ($1)")
+    let judge = sqlx::query_as::<_, Judge>("SELECT * FROM judges WHERE id = ($1)")
         .bind(&judge_id)
-        .fetch_one(&pool)
-        .await;
-
-    match res {
-        Ok(judge) => Ok(axum::Json(judge)),
-        Err(err) => Err(AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to get judge: {}", err),
-        )),
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Judge not found"))?;
+
+    Ok(axum::Json(judge))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWeight {
+    weight: Decimal,
+}
+
+// Weights are part of the setup, they can't change once the event is live
+pub async fn update_weight(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+    axum::Json(payload): axum::Json<UpdateWeight>,
+) -> Result<axum::Json<Judge>, AppError> {
+    validate_weight(payload.weight)?;
+
+    let event_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT event_id FROM judges WHERE id = ($1)")
+            .bind(judge_id)
+            .fetch_one(&pool)
+            .await?;
+
+    event::ensure_setup(&pool, event_id).await?;
+
+    let judge =
+        sqlx::query_as::<_, Judge>("UPDATE judges SET weight = ($1) WHERE id = ($2) RETURNING *")
+            .bind(payload.weight)
+            .bind(judge_id)
+            .fetch_one(&pool)
+            .await?;
+
+    Ok(axum::Json(judge))
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct JudgeExclusionLog {
+    id: uuid::Uuid,
+    judge_id: uuid::Uuid,
+    excluded: bool,
+    reason: String,
+    changed_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateExclusion {
+    excluded: bool,
+    reason: String,
+}
+
+// Excludes a judge from (or reinstates them into) every tabulation and export
+pub async fn update_exclusion(
+    extract::State(pool): extract::State<PgPool>,
+    extract::State(realtime): extract::State<Realtime>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+    axum::Json(payload): axum::Json<UpdateExclusion>,
+) -> Result<axum::Json<Judge>, AppError> {
+    if payload.reason.trim().is_empty() {
+        return Err(AppError::validation(
+            "A reason is required to change a judge's exclusion",
+        ));
     }
+
+    let event_id =
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT event_id FROM judges WHERE id = ($1)")
+            .bind(judge_id)
+            .fetch_one(&pool)
+            .await?;
+
+    event::ensure_changes(&pool, event_id).await?;
+
+    let mut txn = pool.begin().await?;
+
+    let judge = sqlx::query_as::<_, Judge>(
+        "UPDATE judges SET score_exclusion = ($1) WHERE id = ($2) RETURNING *",
+    )
+    .bind(payload.excluded)
+    .bind(judge_id)
+    .fetch_one(&mut *txn)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO judge_exclusion_logs (judge_id, excluded, reason) VALUES ($1, $2, $3)",
+    )
+    .bind(judge_id)
+    .bind(payload.excluded)
+    .bind(payload.reason.trim())
+    .execute(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    println!(
+        "Judge {} {}: {}\n",
+        judge.name,
+        if payload.excluded {
+            "excluded"
+        } else {
+            "reinstated"
+        },
+        payload.reason.trim()
+    );
+
+    let notification = json!({
+        "type": "judge_exclusion",
+        "event_id": judge.event_id,
+        "judge_id": judge.id,
+        "excluded": judge.score_exclusion,
+    });
+
+    realtime.publish(&notification.to_string());
+
+    Ok(axum::Json(judge))
+}
+
+pub async fn get_exclusion_logs(
+    extract::State(pool): extract::State<PgPool>,
+    extract::Path(judge_id): extract::Path<uuid::Uuid>,
+) -> Result<axum::Json<Vec<JudgeExclusionLog>>, AppError> {
+    let logs = sqlx::query_as::<_, JudgeExclusionLog>(
+        "SELECT * FROM judge_exclusion_logs WHERE judge_id = ($1) ORDER BY changed_at DESC",
+    )
+    .bind(judge_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(logs))
 }
@@ -1,22 +1,23 @@
 use sqlx::FromRow;
 
+pub mod affiliation;
+pub mod analytics;
+pub mod assignment;
 pub mod auth;
 pub mod candidate;
 pub mod category;
+pub mod chain;
 pub mod college;
+pub mod correction;
 pub mod criteria;
+pub mod deduction;
 pub mod event;
 pub mod judge;
 pub mod note;
+pub mod progress;
+pub mod readiness;
+pub mod results;
 pub mod score;
+pub mod simulation;
+pub mod tabulation;
 pub mod tests;
-
-pub trait Round {
-    fn round_to_two_decimals(&self) -> f64;
-}
-
-impl Round for f64 {
-    fn round_to_two_decimals(&self) -> f64 {
-        (self * 100.0).round() / 100.0
-    }
-}
@@ -4,6 +4,8 @@ use axum::response::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 
+use crate::error::AppError;
+
 #[derive(Debug, Serialize, FromRow)]
 pub struct Note {
     id: uuid::Uuid,
@@ -24,8 +26,8 @@ pub struct CreateNote {
 pub async fn create_note(
     State(pool): State<PgPool>,
     axum::Json(payload): axum::Json<CreateNote>,
-) -> Result<(http::StatusCode, axum::Json<Note>), http::StatusCode> {
-    let res = sqlx::query_as::<_, Note>(
+) -> Result<(http::StatusCode, axum::Json<Note>), AppError> {
+    let note = sqlx::query_as::<_, Note>(
         r#"
         INSERT INTO notes (note, candidate_id, judge_id) 
         VALUES ($1, $2, $3)
@@ -36,16 +38,9 @@ pub async fn create_note(
     .bind(&payload.candidate_id)
     .bind(&payload.judge_id)
     .fetch_one(&pool)
-    .await;
-
-    match res {
-        Ok(note) => Ok((http::StatusCode::CREATED, axum::Json(note))),
-        Err(err) => {
-            eprintln!("Failed to create note: {err:?}");
+    .await?;
 
-            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok((http::StatusCode::CREATED, axum::Json(note)))
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,17 +51,11 @@ pub struct NoteQuery {
 pub async fn get_note(
     State(pool): State<PgPool>,
     Query(query): Query<NoteQuery>,
-) -> Result<axum::Json<Vec<Note>>, http::StatusCode> {
-    let res = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE candidate_id = ($1)")
+) -> Result<axum::Json<Vec<Note>>, AppError> {
+    let notes = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE candidate_id = ($1)")
         .bind(&query.candidate_id)
         .fetch_all(&pool)
-        .await;
+        .await?;
 
-    match res {
-        Ok(notes) => Ok(axum::Json(notes)),
-        Err(err) => {
-            eprintln!("Failed to get note: {err:?}");
-            Err(http::StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(axum::Json(notes))
 }
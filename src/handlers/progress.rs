@@ -0,0 +1,399 @@
+use std::collections::HashSet;
+
+use axum::extract::{Query, State};
+use axum::http;
+use axum::response::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+use crate::realtime::Realtime;
+
+use super::assignment;
+use super::category::Category;
+use super::tabulation;
+
+#[derive(Debug, FromRow)]
+pub struct ProgressJudge {
+    pub id: uuid::Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, FromRow)]
+pub struct ProgressCandidate {
+    pub id: uuid::Uuid,
+    pub candidate_number: i32,
+    pub gender: i32,
+    // Candidates without a category aren't part of any event
+    pub event_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ProgressCriteria {
+    pub id: uuid::Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryProgress {
+    pub category_id: uuid::Uuid,
+    pub category_name: String,
+    pub event_id: uuid::Uuid,
+    // Column order of the `scored` matrix below
+    pub criterias: Vec<ProgressCriteria>,
+    pub expected: usize,
+    pub submitted: usize,
+    pub missing: usize,
+    pub judges: Vec<JudgeProgress>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JudgeProgress {
+    pub judge_id: uuid::Uuid,
+    pub name: String,
+    pub submitted: usize,
+    pub missing: usize,
+    pub complete: bool,
+    pub candidates: Vec<CandidateProgress>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CandidateProgress {
+    pub candidate_id: uuid::Uuid,
+    pub candidate_number: i32,
+    pub gender: i32,
+    // One entry per criteria, in the same order as `criterias`
+    pub scored: Vec<bool>,
+    pub missing: usize,
+    // The judge is not supposed to score this candidate, nothing is missing
+    pub conflict_of_interest: bool,
+}
+
+// Builds the judge x candidate x criteria matrix from the (judge, candidate, criteria)
+// triples that have a score. Only the candidates of the category's event are in it, and
+// (judge, candidate) pairs with a conflict of interest are not expected to be scored.
+pub fn compute_progress(
+    category: &Category,
+    judges: Vec<ProgressJudge>,
+    candidates: &[ProgressCandidate],
+    criterias: Vec<ProgressCriteria>,
+    scored: &HashSet<(uuid::Uuid, uuid::Uuid, uuid::Uuid)>,
+    conflicts: &HashSet<(uuid::Uuid, uuid::Uuid)>,
+) -> CategoryProgress {
+    let mut judge_progress = Vec::with_capacity(judges.len());
+
+    for judge in judges {
+        let candidate_progress: Vec<CandidateProgress> = candidates
+            .iter()
+            .filter(|candidate| candidate.event_id == Some(category.event_id))
+            .map(|candidate| {
+                let cells: Vec<bool> = criterias
+                    .iter()
+                    .map(|criteria| scored.contains(&(judge.id, candidate.id, criteria.id)))
+                    .collect();
+
+                let conflict_of_interest = conflicts.contains(&(judge.id, candidate.id));
+                let missing = if conflict_of_interest {
+                    0
+                } else {
+                    cells.iter().filter(|scored| !**scored).count()
+                };
+
+                CandidateProgress {
+                    candidate_id: candidate.id,
+                    candidate_number: candidate.candidate_number,
+                    gender: candidate.gender,
+                    scored: cells,
+                    missing,
+                    conflict_of_interest,
+                }
+            })
+            .collect();
+
+        let missing: usize = candidate_progress.iter().map(|c| c.missing).sum();
+        let expected = candidate_progress
+            .iter()
+            .filter(|c| !c.conflict_of_interest)
+            .count()
+            * criterias.len();
+
+        judge_progress.push(JudgeProgress {
+            judge_id: judge.id,
+            name: judge.name,
+            submitted: expected - missing,
+            missing,
+            complete: missing == 0,
+            candidates: candidate_progress,
+        });
+    }
+
+    let expected: usize = judge_progress.iter().map(|j| j.submitted + j.missing).sum();
+    let missing: usize = judge_progress.iter().map(|j| j.missing).sum();
+
+    CategoryProgress {
+        category_id: category.id,
+        category_name: category.name.clone(),
+        event_id: category.event_id,
+        criterias,
+        expected,
+        submitted: expected - missing,
+        missing,
+        judges: judge_progress,
+    }
+}
+
+pub async fn fetch_progress(
+    pool: &PgPool,
+    category_id: uuid::Uuid,
+) -> Result<CategoryProgress, AppError> {
+    let category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = ($1)")
+        .bind(category_id)
+        .fetch_one(pool)
+        .await?;
+
+    let judges = assignment::fetch_panel(pool, category.id)
+        .await?
+        .into_iter()
+        .map(|panel_judge| ProgressJudge {
+            id: panel_judge.judge.id,
+            name: panel_judge.name,
+        })
+        .collect();
+
+    let candidates = sqlx::query_as::<_, ProgressCandidate>(
+        r#"
+        SELECT c.id, c.candidate_number, c.gender, cat.event_id
+        FROM candidates c
+        LEFT JOIN categories cat ON cat.id = c.category_id
+        ORDER BY
+            CASE
+                WHEN c.gender = 1 THEN 1
+                ELSE 2
+            END,
+            c.candidate_number
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let criterias = sqlx::query_as::<_, ProgressCriteria>(
+        "SELECT id, name FROM criterias WHERE category_id = ($1) ORDER BY name",
+    )
+    .bind(category.id)
+    .fetch_all(pool)
+    .await?;
+
+    let scored: HashSet<(uuid::Uuid, uuid::Uuid, uuid::Uuid)> = sqlx::query_as::<
+        _,
+        (uuid::Uuid, uuid::Uuid, uuid::Uuid),
+    >(
+        "SELECT DISTINCT judge_id, candidate_id, criteria_id FROM scores WHERE category_id = ($1)",
+    )
+    .bind(category.id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let conflicts = tabulation::fetch_conflicts(pool).await?;
+
+    Ok(compute_progress(
+        &category,
+        judges,
+        &candidates,
+        criterias,
+        &scored,
+        &conflicts,
+    ))
+}
+
+// A judge that hasn't scored a candidate on every criteria of a category
+#[derive(Debug, Serialize)]
+pub struct IncompleteBallot {
+    pub category_id: uuid::Uuid,
+    pub category_name: String,
+    pub judge_id: uuid::Uuid,
+    pub judge_name: String,
+    pub candidate_id: uuid::Uuid,
+    pub candidate_number: i32,
+    pub gender: i32,
+    pub missing_criterias: Vec<String>,
+}
+
+impl CategoryProgress {
+    pub fn incomplete_ballots(&self) -> Vec<IncompleteBallot> {
+        let mut ballots = Vec::new();
+
+        for judge in self.judges.iter().filter(|judge| !judge.complete) {
+            for candidate in judge.candidates.iter().filter(|c| c.missing > 0) {
+                let missing_criterias = self
+                    .criterias
+                    .iter()
+                    .zip(candidate.scored.iter())
+                    .filter(|(_, scored)| !**scored)
+                    .map(|(criteria, _)| criteria.name.clone())
+                    .collect();
+
+                ballots.push(IncompleteBallot {
+                    category_id: self.category_id,
+                    category_name: self.category_name.clone(),
+                    judge_id: judge.judge_id,
+                    judge_name: judge.name.clone(),
+                    candidate_id: candidate.candidate_id,
+                    candidate_number: candidate.candidate_number,
+                    gender: candidate.gender,
+                    missing_criterias,
+                });
+            }
+        }
+
+        ballots
+    }
+}
+
+// Every ballot of the event that is still missing scores. Without an event, only the events
+// that are being or have been scored count, a draft event never blocks the results.
+pub async fn fetch_incomplete_ballots(
+    pool: &PgPool,
+    event_id: Option<uuid::Uuid>,
+) -> Result<Vec<IncompleteBallot>, AppError> {
+    let category_ids = sqlx::query_scalar::<_, uuid::Uuid>(
+        r#"
+        SELECT c.id
+        FROM categories c
+        JOIN events e ON e.id = c.event_id
+        WHERE (($1)::UUID IS NULL AND e.status IN ('live', 'closed')) OR e.id = ($1)
+        ORDER BY c.name
+        "#,
+    )
+    .bind(event_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut ballots = Vec::new();
+
+    for category_id in category_ids {
+        let progress = fetch_progress(pool, category_id).await?;
+
+        ballots.extend(progress.incomplete_ballots());
+    }
+
+    Ok(ballots)
+}
+
+// Refuses to go on with incomplete ballots unless explicitly allowed, in which case the
+// returned headers carry a warning
+pub async fn ensure_complete(
+    pool: &PgPool,
+    event_id: Option<uuid::Uuid>,
+    allow_incomplete: bool,
+) -> Result<http::HeaderMap, AppError> {
+    let incomplete_ballots = fetch_incomplete_ballots(pool, event_id).await?;
+    let mut headers = http::HeaderMap::new();
+
+    if incomplete_ballots.is_empty() {
+        return Ok(headers);
+    }
+
+    if !allow_incomplete {
+        return Err(AppError::conflict(format!(
+            "{} incomplete ballot(s), see /scores/validation or pass allow_incomplete=true",
+            incomplete_ballots.len()
+        )));
+    }
+
+    headers.insert(
+        http::header::WARNING,
+        http::HeaderValue::from_str(&format!(
+            "199 - \"Computed with {} incomplete ballot(s)\"",
+            incomplete_ballots.len()
+        ))
+        .unwrap(),
+    );
+
+    Ok(headers)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoreValidation {
+    complete: bool,
+    incomplete_ballots: Vec<IncompleteBallot>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ValidationParam {
+    event_id: Option<uuid::Uuid>,
+}
+
+pub async fn get_validation(
+    State(pool): State<PgPool>,
+    Query(param): Query<ValidationParam>,
+) -> Result<axum::Json<ScoreValidation>, AppError> {
+    let incomplete_ballots = fetch_incomplete_ballots(&pool, param.event_id).await?;
+
+    Ok(axum::Json(ScoreValidation {
+        complete: incomplete_ballots.is_empty(),
+        incomplete_ballots,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressParam {
+    category_id: Option<uuid::Uuid>,
+}
+
+// Defaults to the category that is currently active, when more than one event is live it has to
+// be given
+pub async fn get_progress(
+    State(pool): State<PgPool>,
+    Query(param): Query<ProgressParam>,
+) -> Result<axum::Json<CategoryProgress>, AppError> {
+    let category_id = match param.category_id {
+        Some(category_id) => category_id,
+        None => {
+            let active = sqlx::query_scalar::<_, uuid::Uuid>(
+                r#"
+                SELECT c.id
+                FROM categories c
+                JOIN events e ON e.id = c.event_id
+                WHERE c.is_active = TRUE AND e.status = 'live'
+                LIMIT 2
+                "#,
+            )
+            .fetch_all(&pool)
+            .await?;
+
+            match active.as_slice() {
+                [category_id] => *category_id,
+                [] => return Err(AppError::not_found("No active category")),
+                _ => {
+                    return Err(AppError::bad_request(
+                        "More than one event is live, category_id is required",
+                    ))
+                }
+            }
+        }
+    };
+
+    let progress = fetch_progress(&pool, category_id).await?;
+
+    Ok(axum::Json(progress))
+}
+
+// Push the new progress of a category to the tabulator
+pub async fn publish_progress(pool: &PgPool, realtime: &Realtime, category_id: uuid::Uuid) {
+    match fetch_progress(pool, category_id).await {
+        Ok(progress) => {
+            let payload = json!({
+                "type": "progress",
+                "event_id": progress.event_id,
+                "category_id": progress.category_id,
+                "progress": progress,
+            });
+
+            realtime.publish(&payload.to_string());
+        }
+        Err(err) => eprintln!("Failed to compute scoring progress: {err:?}"),
+    }
+}
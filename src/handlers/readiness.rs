@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::extract::{Path, State};
+use axum::response::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+
+use super::assignment;
+use super::category::Category;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReadinessCriteria {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub max_score: Option<Decimal>,
+    pub weight: Option<Decimal>,
+    pub category_id: uuid::Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReadinessJudge {
+    pub id: uuid::Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessCategory {
+    id: uuid::Uuid,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    // Should be exactly 1, i.e. 100%
+    pub weight_sum: Decimal,
+    pub categories_without_criteria: Vec<ReadinessCategory>,
+    pub criterias_without_max_score: Vec<ReadinessCriteria>,
+    pub judges_without_assignment: Vec<ReadinessJudge>,
+    // Everything above in plain words
+    pub issues: Vec<String>,
+}
+
+pub fn check_readiness(
+    categories: &[Category],
+    criterias: &[ReadinessCriteria],
+    judges: &[ReadinessJudge],
+    // category_id -> judges on its panel, see assignment::fetch_assignments
+    assignments: &HashMap<uuid::Uuid, HashSet<uuid::Uuid>>,
+) -> Readiness {
+    let mut issues = Vec::new();
+
+    let weight_sum: Decimal = categories.iter().map(|category| category.weight).sum();
+
+    if weight_sum != Decimal::ONE {
+        issues.push(format!(
+            "Category weights add up to {}% instead of 100%",
+            (weight_sum * Decimal::ONE_HUNDRED).normalize()
+        ));
+    }
+
+    let categories_without_criteria: Vec<ReadinessCategory> = categories
+        .iter()
+        .filter(|category| {
+            !criterias
+                .iter()
+                .any(|criteria| criteria.category_id == category.id)
+        })
+        .map(|category| ReadinessCategory {
+            id: category.id,
+            name: category.name.clone(),
+        })
+        .collect();
+
+    for category in categories_without_criteria.iter() {
+        issues.push(format!("{} has no criterias", category.name));
+    }
+
+    // Weights on only some of the criterias are ignored, which is most likely a mistake
+    for category in categories.iter() {
+        let weighted: Vec<bool> = criterias
+            .iter()
+            .filter(|criteria| criteria.category_id == category.id)
+            .map(|criteria| criteria.weight.is_some())
+            .collect();
+
+        if weighted.contains(&true) && weighted.contains(&false) {
+            issues.push(format!(
+                "{} has weights on only some of its criterias",
+                category.name
+            ));
+        }
+    }
+
+    let criterias_without_max_score: Vec<ReadinessCriteria> = criterias
+        .iter()
+        .filter(|criteria| {
+            criteria
+                .max_score
+                .is_none_or(|max_score| max_score <= Decimal::ZERO)
+        })
+        .cloned()
+        .collect();
+
+    for criteria in criterias_without_max_score.iter() {
+        issues.push(format!("{} has no max score", criteria.name));
+    }
+
+    if judges.is_empty() {
+        issues.push("Event has no judges".to_string());
+    }
+
+    // Judges that are on none of the panels have nothing to score
+    let judges_without_assignment: Vec<ReadinessJudge> = judges
+        .iter()
+        .filter(|judge| {
+            !categories
+                .iter()
+                .any(|category| assignment::is_assigned(assignments, judge.id, category.id))
+        })
+        .cloned()
+        .collect();
+
+    for judge in judges_without_assignment.iter() {
+        issues.push(format!("{} is not assigned to any category", judge.name));
+    }
+
+    Readiness {
+        ready: issues.is_empty(),
+        weight_sum,
+        categories_without_criteria,
+        criterias_without_max_score,
+        judges_without_assignment,
+        issues,
+    }
+}
+
+pub async fn fetch_readiness(pool: &PgPool, event_id: uuid::Uuid) -> Result<Readiness, AppError> {
+    let categories = sqlx::query_as::<_, Category>(
+        "SELECT * FROM categories WHERE event_id = ($1) ORDER BY name",
+    )
+    .bind(event_id)
+    .fetch_all(pool)
+    .await?;
+
+    let criterias = sqlx::query_as::<_, ReadinessCriteria>(
+        r#"
+        SELECT cr.id, cr.name, cr.max_score, cr.weight, cr.category_id
+        FROM criterias cr
+        JOIN categories c ON c.id = cr.category_id
+        WHERE c.event_id = ($1)
+        ORDER BY c.name, cr.name
+        "#,
+    )
+    .bind(event_id)
+    .fetch_all(pool)
+    .await?;
+
+    let judges = sqlx::query_as::<_, ReadinessJudge>(
+        "SELECT id, name FROM judges WHERE event_id = ($1) ORDER BY name",
+    )
+    .bind(event_id)
+    .fetch_all(pool)
+    .await?;
+
+    let assignments = assignment::fetch_assignments(pool).await?;
+
+    Ok(check_readiness(
+        &categories,
+        &criterias,
+        &judges,
+        &assignments,
+    ))
+}
+
+// Whether an event is set up properly before it goes live
+pub async fn get_readiness(
+    State(pool): State<PgPool>,
+    Path(event_id): Path<uuid::Uuid>,
+) -> Result<axum::Json<Readiness>, AppError> {
+    let readiness = fetch_readiness(&pool, event_id).await?;
+
+    Ok(axum::Json(readiness))
+}
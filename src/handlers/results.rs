@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+
+use axum::extract::{Path, Query, State};
+use axum::http;
+use axum::response::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::types::Json;
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+use crate::realtime::Realtime;
+
+use super::chain::{self, ChainHead};
+use super::progress;
+use super::tabulation::{
+    self, CandidateResult, CategoryBreakdown, Method, RoundingPolicy, TabulationInput,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishedResult {
+    candidate_id: uuid::Uuid,
+    candidate_number: i32,
+    first_name: String,
+    middle_name: String,
+    last_name: String,
+    gender: i32,
+    final_score: Decimal,
+    rank: u32,
+}
+
+impl PublishedResult {
+    pub fn new(result: CandidateResult, rounding: &RoundingPolicy) -> Self {
+        Self {
+            candidate_id: result.candidate.id,
+            candidate_number: result.candidate.candidate_number,
+            first_name: result.candidate.first_name,
+            middle_name: result.candidate.middle_name,
+            last_name: result.candidate.last_name,
+            gender: result.candidate.gender,
+            final_score: rounding.apply(result.final_score),
+            rank: result.rank,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SnapshotJudge {
+    judge_id: uuid::Uuid,
+    name: String,
+    event_id: uuid::Uuid,
+}
+
+// Everything that goes into the content hash of a snapshot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotContent {
+    method: Method,
+    ranking: Vec<PublishedResult>,
+    breakdown: Vec<CategoryBreakdown>,
+    judges: Vec<SnapshotJudge>,
+    // Snapshots published before the score chain existed don't have these
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    chain_heads: Vec<ChainHead>,
+    // Snapshots published before the rounding policy existed don't have it either
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rounding: Option<RoundingPolicy>,
+    // Only set when the results of a single event were published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    event_id: Option<uuid::Uuid>,
+}
+
+impl SnapshotContent {
+    // What gets stored and hashed when publishing
+    pub fn canonical(&self) -> String {
+        serde_json::to_string(self).expect("Snapshot content is always serializable")
+    }
+
+    pub fn hash(&self) -> String {
+        content_hash(&self.canonical())
+    }
+}
+
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+#[derive(Debug, FromRow)]
+struct SnapshotRow {
+    id: uuid::Uuid,
+    published_at: chrono::DateTime<chrono::Utc>,
+    method: String,
+    ranking: Json<Value>,
+    breakdown: Json<Value>,
+    judges: Json<Value>,
+    chain_heads: Json<Value>,
+    rounding: Option<Json<Value>>,
+    content_hash: String,
+    content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    id: uuid::Uuid,
+    published_at: chrono::DateTime<chrono::Utc>,
+    content_hash: String,
+    // The stored content still matches the stored hash
+    hash_valid: bool,
+    // Exactly as it was published, never read back into today's types
+    #[serde(flatten)]
+    content: Value,
+}
+
+// The parts of a snapshot that are compared with the live results
+#[derive(Debug, Deserialize)]
+struct PublishedRanking {
+    ranking: Vec<PublishedResult>,
+    judges: Vec<SnapshotJudge>,
+    #[serde(default)]
+    event_id: Option<uuid::Uuid>,
+}
+
+impl Snapshot {
+    pub fn new(
+        id: uuid::Uuid,
+        published_at: chrono::DateTime<chrono::Utc>,
+        content: &str,
+        content_hash: String,
+    ) -> Result<Self, AppError> {
+        let hash_valid = self::content_hash(content) == content_hash;
+        let content = serde_json::from_str(content)
+            .map_err(|err| AppError::internal(format!("Snapshot content is not JSON: {}", err)))?;
+
+        Ok(Self {
+            id,
+            published_at,
+            content_hash,
+            hash_valid,
+            content,
+        })
+    }
+
+    // The event the snapshot was published for, None when it covers every event
+    pub fn event_id(&self) -> Result<Option<uuid::Uuid>, AppError> {
+        Ok(self.published()?.event_id)
+    }
+
+    fn published(&self) -> Result<PublishedRanking, AppError> {
+        PublishedRanking::deserialize(&self.content).map_err(|err| {
+            AppError::internal(format!("Failed to read the snapshot's ranking: {}", err))
+        })
+    }
+}
+
+impl TryFrom<SnapshotRow> for Snapshot {
+    type Error = AppError;
+
+    fn try_from(row: SnapshotRow) -> Result<Self, Self::Error> {
+        if let Some(content) = row.content {
+            return Snapshot::new(row.id, row.published_at, &content, row.content_hash);
+        }
+
+        // Published before the content was stored as is, the best we can do is serializing it
+        // again the same way and hope nothing about the types changed since
+        let mut content = json!({
+            "method": row.method,
+            "ranking": row.ranking.0,
+            "breakdown": row.breakdown.0,
+            "judges": row.judges.0,
+            "chain_heads": row.chain_heads.0,
+        });
+
+        if let Some(rounding) = row.rounding {
+            content["rounding"] = rounding.0;
+        }
+
+        let hash_valid = serde_json::from_value::<SnapshotContent>(content.clone())
+            .is_ok_and(|typed| typed.hash() == row.content_hash);
+
+        Ok(Self {
+            id: row.id,
+            published_at: row.published_at,
+            content_hash: row.content_hash,
+            hash_valid,
+            content,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SnapshotSummary {
+    id: uuid::Uuid,
+    published_at: chrono::DateTime<chrono::Utc>,
+    method: String,
+    content_hash: String,
+}
+
+// What gets stored as the official results when publishing
+pub fn ranking(
+    input: &TabulationInput,
+    method: Method,
+    rounding: &RoundingPolicy,
+) -> Vec<PublishedResult> {
+    tabulation::tabulate_with(input, method)
+        .into_iter()
+        .map(|result| PublishedResult::new(result, rounding))
+        .collect()
+}
+
+pub fn snapshot_content(
+    input: &TabulationInput,
+    judges: Vec<SnapshotJudge>,
+    chain_heads: Vec<ChainHead>,
+    rounding: RoundingPolicy,
+    event_id: Option<uuid::Uuid>,
+) -> SnapshotContent {
+    let method = Method::default();
+
+    SnapshotContent {
+        method,
+        ranking: ranking(input, method, &rounding),
+        breakdown: tabulation::category_breakdown(input)
+            .into_iter()
+            .map(|breakdown| CategoryBreakdown {
+                percentage: rounding.apply(breakdown.percentage),
+                ..breakdown
+            })
+            .collect(),
+        judges,
+        chain_heads,
+        rounding: Some(rounding),
+        event_id,
+    }
+}
+
+// The results as they would be published right now, of a single event or of all of them
+async fn live_content(
+    pool: &PgPool,
+    event_id: Option<uuid::Uuid>,
+) -> Result<SnapshotContent, AppError> {
+    let mut input = tabulation::load_input(pool).await?;
+
+    if let Some(event_id) = event_id {
+        input = input.for_event(event_id);
+    }
+
+    let judges = sqlx::query_as::<_, SnapshotJudge>(
+        r#"
+        SELECT id AS judge_id, name, event_id FROM judges
+        WHERE score_exclusion = FALSE AND (($1)::UUID IS NULL OR event_id = ($1))
+        ORDER BY name, id
+        "#,
+    )
+    .bind(event_id)
+    .fetch_all(pool)
+    .await?;
+
+    let chain_heads = chain::fetch_heads(pool)
+        .await?
+        .into_iter()
+        .filter(|head| event_id.is_none_or(|event_id| head.event_id == event_id))
+        .collect();
+
+    Ok(snapshot_content(
+        &input,
+        judges,
+        chain_heads,
+        RoundingPolicy::from_env(),
+        event_id,
+    ))
+}
+
+async fn fetch_snapshot(pool: &PgPool, snapshot_id: uuid::Uuid) -> Result<Snapshot, AppError> {
+    let row = sqlx::query_as::<_, SnapshotRow>("SELECT * FROM results_snapshots WHERE id = ($1)")
+        .bind(snapshot_id)
+        .fetch_one(pool)
+        .await?;
+
+    Snapshot::try_from(row)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PublishParam {
+    allow_incomplete: Option<bool>,
+    // Only this event's ballots are checked and only its candidates are ranked and written
+    event_id: Option<uuid::Uuid>,
+}
+
+// The only place where the official final scores are written, every publish is kept as
+// an immutable snapshot
+pub async fn publish_results(
+    State(pool): State<PgPool>,
+    State(realtime): State<Realtime>,
+    Query(param): Query<PublishParam>,
+) -> Result<(http::StatusCode, http::HeaderMap, axum::Json<Snapshot>), AppError> {
+    let headers = progress::ensure_complete(
+        &pool,
+        param.event_id,
+        param.allow_incomplete.unwrap_or(false),
+    )
+    .await?;
+
+    let content = live_content(&pool, param.event_id).await?;
+    let canonical = content.canonical();
+    let content_hash = content_hash(&canonical);
+
+    let mut txn = pool.begin().await?;
+
+    for result in content.ranking.iter() {
+        sqlx::query("UPDATE candidates SET final_score = ($1) WHERE id = ($2)")
+            .bind(result.final_score)
+            .bind(result.candidate_id)
+            .execute(&mut *txn)
+            .await?;
+    }
+
+    let (id, published_at) = sqlx::query_as::<_, (uuid::Uuid, chrono::DateTime<chrono::Utc>)>(
+        r#"
+        INSERT INTO results_snapshots (method, ranking, breakdown, judges, chain_heads,
+            rounding, content_hash, content)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, published_at
+        "#,
+    )
+    .bind(
+        json!(content.method)
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    )
+    .bind(Json(&content.ranking))
+    .bind(Json(&content.breakdown))
+    .bind(Json(&content.judges))
+    .bind(Json(&content.chain_heads))
+    .bind(content.rounding.map(Json))
+    .bind(&content_hash)
+    .bind(&canonical)
+    .fetch_one(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    println!(
+        "Results published for {} candidates, snapshot {} ({}).\n",
+        content.ranking.len(),
+        id,
+        content_hash
+    );
+
+    let notification = json!({
+        "type": "results_published",
+        "event_id": param.event_id,
+        "snapshot_id": id,
+        "published_at": published_at,
+        "content_hash": content_hash,
+        "results": content.ranking,
+    });
+
+    realtime.publish(&notification.to_string());
+
+    let snapshot = Snapshot::new(id, published_at, &canonical, content_hash)?;
+
+    Ok((http::StatusCode::CREATED, headers, axum::Json(snapshot)))
+}
+
+pub async fn get_snapshots(
+    State(pool): State<PgPool>,
+) -> Result<axum::Json<Vec<SnapshotSummary>>, AppError> {
+    let snapshots = sqlx::query_as::<_, SnapshotSummary>(
+        r#"
+        SELECT id, published_at, method, content_hash
+        FROM results_snapshots
+        ORDER BY published_at DESC
+        "#,
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(axum::Json(snapshots))
+}
+
+pub async fn get_snapshot(
+    State(pool): State<PgPool>,
+    Path(snapshot_id): Path<uuid::Uuid>,
+) -> Result<axum::Json<Snapshot>, AppError> {
+    let snapshot = fetch_snapshot(&pool, snapshot_id).await?;
+
+    Ok(axum::Json(snapshot))
+}
+
+pub async fn download_snapshot(
+    State(pool): State<PgPool>,
+    Path(snapshot_id): Path<uuid::Uuid>,
+) -> Result<(http::HeaderMap, Vec<u8>), AppError> {
+    let snapshot = fetch_snapshot(&pool, snapshot_id).await?;
+
+    let body = serde_json::to_vec_pretty(&snapshot)
+        .map_err(|err| AppError::internal(format!("Failed to serialize snapshot: {}", err)))?;
+
+    let mut headers = http::HeaderMap::new();
+
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json"),
+    );
+    headers.insert(
+        http::header::CONTENT_DISPOSITION,
+        http::HeaderValue::from_str(&format!(
+            "attachment; filename=\"results-{}.json\"",
+            snapshot.id
+        ))
+        .unwrap(),
+    );
+
+    Ok((headers, body))
+}
+
+#[derive(Debug, Serialize)]
+pub struct CandidateDiff {
+    candidate_id: uuid::Uuid,
+    candidate_number: i32,
+    gender: i32,
+    snapshot_score: Option<Decimal>,
+    live_score: Option<Decimal>,
+    score_delta: Option<Decimal>,
+    snapshot_rank: Option<u32>,
+    live_rank: Option<u32>,
+    // Positive when the candidate moved up since the snapshot
+    rank_delta: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotDiff {
+    snapshot_id: uuid::Uuid,
+    snapshot_hash: String,
+    live_hash: String,
+    changed: bool,
+    candidates: Vec<CandidateDiff>,
+    judges_added: Vec<SnapshotJudge>,
+    judges_removed: Vec<SnapshotJudge>,
+}
+
+pub fn diff(snapshot: &Snapshot, live: &SnapshotContent) -> Result<SnapshotDiff, AppError> {
+    let published = snapshot.published()?;
+
+    let mut candidates: HashMap<uuid::Uuid, CandidateDiff> = HashMap::new();
+
+    for result in published.ranking.iter() {
+        candidates.insert(
+            result.candidate_id,
+            CandidateDiff {
+                candidate_id: result.candidate_id,
+                candidate_number: result.candidate_number,
+                gender: result.gender,
+                snapshot_score: Some(result.final_score),
+                live_score: None,
+                score_delta: None,
+                snapshot_rank: Some(result.rank),
+                live_rank: None,
+                rank_delta: None,
+            },
+        );
+    }
+
+    for result in live.ranking.iter() {
+        let candidate = candidates
+            .entry(result.candidate_id)
+            .or_insert_with(|| CandidateDiff {
+                candidate_id: result.candidate_id,
+                candidate_number: result.candidate_number,
+                gender: result.gender,
+                snapshot_score: None,
+                live_score: None,
+                score_delta: None,
+                snapshot_rank: None,
+                live_rank: None,
+                rank_delta: None,
+            });
+
+        candidate.live_score = Some(result.final_score);
+        candidate.live_rank = Some(result.rank);
+        candidate.score_delta = candidate
+            .snapshot_score
+            .map(|snapshot_score| result.final_score - snapshot_score);
+        candidate.rank_delta = candidate
+            .snapshot_rank
+            .map(|snapshot_rank| snapshot_rank as i64 - result.rank as i64);
+    }
+
+    let mut candidates: Vec<CandidateDiff> = candidates.into_values().collect();
+    candidates.sort_by_key(|candidate| (candidate.gender != 1, candidate.candidate_number));
+
+    let judges_added = live
+        .judges
+        .iter()
+        .filter(|judge| !published.judges.contains(judge))
+        .cloned()
+        .collect();
+    let judges_removed = published
+        .judges
+        .iter()
+        .filter(|judge| !live.judges.contains(judge))
+        .cloned()
+        .collect();
+
+    let live_hash = live.hash();
+
+    Ok(SnapshotDiff {
+        snapshot_id: snapshot.id,
+        changed: live_hash != snapshot.content_hash,
+        snapshot_hash: snapshot.content_hash.clone(),
+        live_hash,
+        candidates,
+        judges_added,
+        judges_removed,
+    })
+}
+
+// What changed between a snapshot and the results computed from the current scores
+pub async fn diff_snapshot(
+    State(pool): State<PgPool>,
+    Path(snapshot_id): Path<uuid::Uuid>,
+) -> Result<axum::Json<SnapshotDiff>, AppError> {
+    let snapshot = fetch_snapshot(&pool, snapshot_id).await?;
+    let live = live_content(&pool, snapshot.event_id()?).await?;
+
+    Ok(axum::Json(diff(&snapshot, &live)?))
+}
@@ -1,29 +1,39 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Context;
 use axum::extract::{Query, State};
 use axum::http;
 use axum::response::Result;
 use chrono::Local;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use rust_xlsxwriter::*;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::query::QueryAs;
 use sqlx::{FromRow, PgPool, Row};
 
 use crate::error::AppError;
+use crate::realtime::Realtime;
 
-use super::category::Category;
+use super::affiliation;
+use super::assignment;
+use super::category::{self, Category};
+use super::chain;
 use super::criteria::Criteria;
-use super::event::Event;
+use super::event;
 use super::judge::Judge;
-use super::Round;
+use super::progress;
+use super::tabulation::{self, CandidateResult, RoundingPolicy, TabulationInput};
 
 #[derive(Debug, Deserialize, Serialize, FromRow)]
 pub struct Score {
     id: uuid::Uuid,
-    score: i32,
-    max: i32,
+    score: Decimal,
+    max: Decimal,
     time_of_scoring: chrono::DateTime<chrono::Utc>,
+    // Goes up on every change, see update_score
+    version: i32,
     // Relationships
     candidate_id: uuid::Uuid,
     criteria_id: uuid::Uuid,
@@ -33,19 +43,83 @@ pub struct Score {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CreateScore {
-    score: i32,
-    max: i32,
+    score: Decimal,
+    max: Decimal,
     candidate_id: uuid::Uuid,
     criteria_id: uuid::Uuid,
     category_id: uuid::Uuid,
     judge_id: uuid::Uuid,
 }
 
+// A score has to be within the criteria's range and land on one of its steps,
+// e.g. 8.5 is fine with a step of 0.5 but 8.25 is not
+pub fn check_score(score: Decimal, max_score: Decimal, score_step: Decimal) -> Result<(), String> {
+    if score < Decimal::ZERO || score > max_score {
+        return Err(format!(
+            "Score must be between 0 and {}",
+            max_score.normalize()
+        ));
+    }
+
+    if !(score % score_step).is_zero() {
+        return Err(format!(
+            "Score must be in steps of {}",
+            score_step.normalize()
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn validate_score(
+    pool: &PgPool,
+    criteria_id: uuid::Uuid,
+    score: Decimal,
+) -> Result<Decimal, AppError> {
+    let (max_score, score_step) = sqlx::query_as::<_, (Decimal, Decimal)>(
+        "SELECT max_score, score_step FROM criterias WHERE id = ($1)",
+    )
+    .bind(criteria_id)
+    .fetch_one(pool)
+    .await?;
+
+    check_score(score, max_score, score_step).map_err(AppError::validation)?;
+
+    Ok(max_score)
+}
+
 // Submit score function for each individual judge
 pub async fn submit_score(
     State(pool): State<PgPool>,
+    State(realtime): State<Realtime>,
     axum::Json(payload): axum::Json<CreateScore>,
 ) -> Result<(http::StatusCode, axum::Json<Score>), AppError> {
+    event::ensure_scoring(&pool, payload.category_id).await?;
+    category::ensure_unlocked(&pool, payload.category_id).await?;
+
+    if !assignment::has_assignment(&pool, payload.judge_id, payload.category_id).await? {
+        return Err(AppError::forbidden(
+            "Judge is not on the panel of this category",
+        ));
+    }
+
+    if affiliation::has_conflict(&pool, payload.judge_id, payload.candidate_id).await? {
+        return Err(AppError::forbidden(
+            "Judge is affiliated with the candidate's college and cannot score them",
+        ));
+    }
+
+    let max_score = validate_score(&pool, payload.criteria_id, payload.score).await?;
+
+    if payload.max != max_score {
+        return Err(AppError::validation(format!(
+            "Max does not match the criteria's max score of {}",
+            max_score.normalize()
+        )));
+    }
+
+    let mut txn = pool.begin().await?;
+
     let res = sqlx::query_as::<_, Score>(
         r#"
         INSERT INTO scores (score, max, candidate_id, criteria_id, category_id, judge_id) 
@@ -59,18 +133,28 @@ pub async fn submit_score(
     .bind(&payload.criteria_id)
     .bind(&payload.category_id)
     .bind(&payload.judge_id)
-    .fetch_one(&pool)
+    .fetch_one(&mut *txn)
     .await;
 
+    let res = match res {
+        Ok(score) => chain::append(&mut txn, "insert", score.id)
+            .await
+            .map(|_| score),
+        Err(err) => Err(err),
+    };
+
     match res {
-        Ok(score) => Ok((http::StatusCode::CREATED, axum::Json(score))),
+        Ok(score) => {
+            txn.commit().await?;
+
+            progress::publish_progress(&pool, &realtime, score.category_id).await;
+
+            Ok((http::StatusCode::CREATED, axum::Json(score)))
+        }
         Err(err) => {
             eprintln!("Failed to submit score: {err:?}");
 
-            Err(AppError::new(
-                http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to submit score: {}", err),
-            ))
+            Err(err.into())
         }
     }
 }
@@ -78,35 +162,84 @@ pub async fn submit_score(
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UpdateScore {
     score_id: uuid::Uuid,
-    score: i32,
+    score: Decimal,
+    // The version of the score the client edited
+    version: i32,
+}
+
+// Someone else changed the score since the client loaded it, send back what it is now so the
+// client can show it instead of overwriting it
+pub fn check_version(version: i32, current: &Score) -> Result<(), AppError> {
+    if current.version != version {
+        return Err(AppError::conflict("Score was changed by someone else")
+            .with_details(json!({ "current": current })));
+    }
+
+    Ok(())
 }
 
 pub async fn update_score(
     State(pool): State<PgPool>,
     axum::Json(payload): axum::Json<UpdateScore>,
 ) -> Result<(http::StatusCode, axum::Json<Score>), AppError> {
+    let (judge_id, candidate_id, criteria_id, category_id) =
+        sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid, uuid::Uuid, uuid::Uuid)>(
+            "SELECT judge_id, candidate_id, criteria_id, category_id FROM scores WHERE id = ($1)",
+        )
+        .bind(&payload.score_id)
+        .fetch_one(&pool)
+        .await?;
+
+    event::ensure_scoring(&pool, category_id).await?;
+    category::ensure_unlocked(&pool, category_id).await?;
+
+    if affiliation::has_conflict(&pool, judge_id, candidate_id).await? {
+        return Err(AppError::forbidden(
+            "Judge is affiliated with the candidate's college and cannot score them",
+        ));
+    }
+
+    validate_score(&pool, criteria_id, payload.score).await?;
+
+    let mut txn = pool.begin().await?;
+
+    let current = sqlx::query_as::<_, Score>("SELECT * FROM scores WHERE id = ($1) FOR UPDATE")
+        .bind(&payload.score_id)
+        .fetch_one(&mut *txn)
+        .await?;
+
+    check_version(payload.version, &current)?;
+
     let res = sqlx::query_as::<_, Score>(
         r#"
-        UPDATE scores SET score = ($1), time_of_scoring = ($2) 
-        WHERE id = ($3) 
+        UPDATE scores SET score = ($1), time_of_scoring = ($2), version = version + 1
+        WHERE id = ($3)
         RETURNING *
         "#,
     )
     .bind(&payload.score)
     .bind(Local::now())
     .bind(&payload.score_id)
-    .fetch_one(&pool)
+    .fetch_one(&mut *txn)
     .await;
 
+    let res = match res {
+        Ok(score) => chain::append(&mut txn, "update", score.id)
+            .await
+            .map(|_| score),
+        Err(err) => Err(err),
+    };
+
     match res {
-        Ok(score) => Ok((http::StatusCode::CREATED, axum::Json(score))),
+        Ok(score) => {
+            txn.commit().await?;
+
+            Ok((http::StatusCode::CREATED, axum::Json(score)))
+        }
         Err(err) => {
             eprintln!("Failed to submit score: {err:?}");
 
-            Err(AppError::new(
-                http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to submit score: {}", err),
-            ))
+            Err(err.into())
         }
     }
 }
@@ -168,21 +301,16 @@ pub async fn get_candidate_score(
         }
     };
 
-    match res {
-        Ok(scores) => Ok(axum::Json(scores)),
-        Err(err) => Err(AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to get candidate scores: {}", err),
-        )),
-    }
+    Ok(axum::Json(res?))
 }
 
-// #[derive(Debug, Deserialize)]
-// pub struct FinalScoreParam {
-//     // candidate_id: uuid::Uuid,
-//     // category_id: uuid::Uuid,
-//     event_id: uuid::Uuid,
-// }
+#[derive(Debug, Deserialize)]
+pub struct FinalScoreParam {
+    // Compute the results even if some judges haven't scored every candidate
+    allow_incomplete: Option<bool>,
+    // Only this event's candidates and ballots instead of every event's
+    event_id: Option<uuid::Uuid>,
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CandidateFinalScore {
@@ -190,7 +318,7 @@ pub struct CandidateFinalScore {
     first_name: String,
     middle_name: String,
     last_name: String,
-    final_score: f32,
+    final_score: Decimal,
 }
 
 // Temporary, might change it
@@ -202,192 +330,62 @@ pub struct CandidateFinalScore2 {
     middle_name: String,
     last_name: String,
     gender: i32,
-    final_score: f32,
-}
-
-#[derive(Debug, Deserialize, Serialize, FromRow)]
-pub struct CandidateScore {
-    candidate_id: uuid::Uuid,
-    candidate_number: i32,
-    first_name: String,
-    middle_name: String,
-    last_name: String,
-    gender: i32,
-    total_score: i64,
-    total_max: i64,
-    weighted_score: f64,
-    weighted_max: f64,
+    final_score: Decimal,
 }
 
-// It works but it might be inefficient
-// Immediately gets the final score of all candidates
+// Computes the final score of all candidates, nothing is saved until the results are published
 pub async fn get_candidate_final_scores(
     State(pool): State<PgPool>,
-) -> Result<axum::Json<Vec<CandidateFinalScore2>>, AppError> {
-    let final_scores = fetch_final_scores(State(pool)).await?;
-
-    Ok(axum::Json(final_scores))
-}
-
-pub async fn fetch_final_scores(
-    State(pool): State<PgPool>,
-    // Query(query): Query<FinalScoreParam>,
-) -> Result<Vec<CandidateFinalScore2>, AppError> {
-    let res = sqlx::query_as::<_, CandidateScore>(
-        r#"
-        SELECT 
-            c.id AS candidate_id,
-            c.candidate_number,
-            c.first_name,
-            c.middle_name,
-            c.last_name,
-            c.gender,
-            COALESCE(SUM(s.score), 0) AS total_score, 
-            COALESCE(SUM(s.max), 0) AS total_max,
-            COALESCE(SUM(s.score), 0) * cat.weight AS weighted_score,
-            COALESCE(SUM(s.max), 0) * cat.weight AS weighted_max
-        FROM 
-            candidates c
-        LEFT JOIN 
-            scores s ON s.candidate_id = c.id
-        LEFT JOIN 
-            categories cat ON s.category_id = cat.id
-        GROUP BY
-            c.id, cat.weight
-        ORDER BY 
-            CASE 
-                WHEN c.gender = 1 THEN 1
-                ELSE 2
-            END,
-            c.candidate_number
-        "#,
+    Query(param): Query<FinalScoreParam>,
+) -> Result<(http::HeaderMap, axum::Json<Vec<CandidateFinalScore2>>), AppError> {
+    let headers = progress::ensure_complete(
+        &pool,
+        param.event_id,
+        param.allow_incomplete.unwrap_or(false),
     )
-    // .bind(&query.event_id)
-    .fetch_all(&pool)
-    .await;
-
-    let mut txn = pool.begin().await?;
-
-    match res {
-        Ok(candidates) => {
-            let mut candidate_final_scores: Vec<CandidateFinalScore2> = Vec::new();
-            let final_scores = calculate_final_scores(&candidates);
-
-            for (
-                candidate_id,
-                (candidate_number, gender, first_name, middle_name, last_name, final_score),
-            ) in final_scores
-            {
-                sqlx::query("UPDATE candidates SET final_score = ($1) WHERE id = ($2) AND final_score <> ($1)")
-                    .bind(final_score)
-                    .bind(candidate_id)
-                    .execute(&mut *txn)
-                    .await?;
-
-                println!(
-                    "Candidate #: {}, Candidate Name: {}, Final Score: {}",
-                    candidate_number, last_name, final_score
-                );
-
-                candidate_final_scores.push(CandidateFinalScore2 {
-                    candidate_id,
-                    candidate_number,
-                    first_name,
-                    middle_name,
-                    last_name,
-                    gender,
-                    final_score,
-                });
-            }
+    .await?;
 
-            txn.commit().await?;
+    let final_scores = fetch_final_scores(&pool, param.event_id).await?;
 
-            Ok(candidate_final_scores)
-        }
-        Err(err) => {
-            eprintln!("Failed to get candidates when computing scores: {err:?}");
-
-            Err(AppError::new(
-                http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to get candidate scores: {}", err),
-            ))
-        }
-    }
+    Ok((headers, axum::Json(final_scores)))
 }
 
-// Tuples here could be structs
-fn calculate_final_scores(
-    scores: &Vec<CandidateScore>,
-) -> Vec<(uuid::Uuid, (i32, i32, String, String, String, f32))> {
-    let mut candidate_scores: HashMap<uuid::Uuid, (i32, i32, String, String, String, f32, f32)> =
-        HashMap::new();
-
-    for score in scores {
-        let (
-            candidate_number,
-            gender,
-            first_name,
-            middle_name,
-            last_name,
-            weighted_scores_sum,
-            weighted_max_sum,
-        ) = candidate_scores.entry(score.candidate_id).or_insert((
-            score.candidate_number,
-            score.gender,
-            score.first_name.clone(),
-            score.middle_name.clone(),
-            score.last_name.clone(),
-            0.0,
-            0.0,
-        ));
-
-        *weighted_scores_sum += score.weighted_score.round_to_two_decimals() as f32;
-        *weighted_max_sum += score.weighted_max.round_to_two_decimals() as f32;
-    }
-
-    // Very bad code (I think) xD
-    let mut final_scores: HashMap<uuid::Uuid, (i32, i32, String, String, String, f32)> =
-        HashMap::new();
+pub async fn fetch_final_scores(
+    pool: &PgPool,
+    event_id: Option<uuid::Uuid>,
+) -> Result<Vec<CandidateFinalScore2>, AppError> {
+    let mut input = tabulation::load_input(pool).await?;
 
-    for (
-        candidate_id,
-        (
-            candidate_number,
-            gender,
-            first_name,
-            middle_name,
-            last_name,
-            weighted_scores_sum,
-            weighted_max_sum,
-        ),
-    ) in candidate_scores.into_iter()
-    {
-        let final_score = (weighted_scores_sum / weighted_max_sum) * 100.0;
-        final_scores.insert(
-            candidate_id,
-            (
-                candidate_number,
-                gender,
-                first_name,
-                middle_name,
-                last_name,
-                final_score,
-            ),
-        );
+    if let Some(event_id) = event_id {
+        input = input.for_event(event_id);
     }
 
-    let mut sorted_final_scores: Vec<_> = final_scores.clone().into_iter().collect();
-
-    // Sory by candidate number because it gets messed up
-    sorted_final_scores.sort_by(|(_, (a, _, _, _, _, _)), (_, (b, _, _, _, _, _))| a.cmp(b));
+    Ok(final_scores(&input, &RoundingPolicy::from_env()))
+}
 
-    sorted_final_scores
+// Pure, the same input always gives the same scores and nothing is written anywhere
+pub fn final_scores(
+    input: &TabulationInput,
+    rounding: &RoundingPolicy,
+) -> Vec<CandidateFinalScore2> {
+    tabulation::tabulate(input)
+        .into_iter()
+        .map(|result| CandidateFinalScore2 {
+            candidate_id: result.candidate.id,
+            candidate_number: result.candidate.candidate_number,
+            first_name: result.candidate.first_name,
+            middle_name: result.candidate.middle_name,
+            last_name: result.candidate.last_name,
+            gender: result.candidate.gender,
+            final_score: rounding.apply(result.final_score),
+        })
+        .collect()
 }
 
 #[derive(Debug, Deserialize, FromRow)]
 pub struct CategoryWeight {
     id: uuid::Uuid,
-    weight: f32,
+    weight: Decimal,
 }
 
 #[derive(Debug, Deserialize, FromRow)]
@@ -406,14 +404,17 @@ pub struct CriteriaIdName {
 
 #[derive(Debug, Deserialize, FromRow)]
 pub struct CriteriaScore {
-    score: i32,
+    score: Decimal,
     judge_name: String,
     candidate_first_name: String,
     candidate_middle_name: String,
     candidate_last_name: String,
-    weight: f32,
-    max: i32,
+    weight: Decimal,
+    max: Decimal,
+    candidate_id: uuid::Uuid,
+    deductions: Decimal,
     event_name: String,
+    judge_weight: Decimal,
 }
 
 #[derive(Debug, FromRow)]
@@ -473,6 +474,10 @@ pub async fn generate_score_spreadsheet(
     .fetch_all(&pool)
     .await?;
 
+    let conflicts = tabulation::fetch_conflicts(&pool).await?;
+    let criteria_weights = tabulation::criteria_weights(&tabulation::fetch_criterias(&pool).await?);
+    let rounding = RoundingPolicy::from_env();
+
     // Could use the Rayon crate for parallelization, but no need
     let (male_candidates, female_candidates): (Vec<&Candidate>, Vec<&Candidate>) = candidates
         .iter()
@@ -491,16 +496,13 @@ pub async fn generate_score_spreadsheet(
         worksheet.write_with_format(1 + row_offset, 0, "Candidate #", &bold_center_format)?;
         worksheet.write_with_format(1 + row_offset, 1, "Name", &bold_center_format)?;
 
-        // Could be improved, it's not necessary to fetch the same judges on the same
-        // event_id
-        // Could use a Hashmap wherein the event_id is they key and the vector of judges
-        // are the values after fetching every judge per event in one SQL query
-        let judges = sqlx::query_as::<_, (uuid::Uuid, String)>(
-            "SELECT id, name FROM judges WHERE event_id = ($1) AND score_exclusion = FALSE",
-        )
-        .bind(&category.event_id)
-        .fetch_all(&pool)
-        .await?;
+        // Only the judges on the category's panel get a column
+        let judges: Vec<(uuid::Uuid, String, Decimal)> =
+            assignment::fetch_panel(&pool, category.id)
+                .await?
+                .into_iter()
+                .map(|panel_judge| (panel_judge.judge.id, panel_judge.name, panel_judge.weight))
+                .collect();
 
         // Please improve this
         if category.name.trim() == "Final Top 10 Candidates" {
@@ -526,32 +528,50 @@ pub async fn generate_score_spreadsheet(
 
             write_by_rank(&pool, worksheet, row_offset + 2, 0).await?;
         } else {
-            // Write judge names
-            for (i, (_, judge_name)) in judges.iter().enumerate() {
+            // Write judge names, along with their weight when they don't count the same as the
+            // rest of the panel
+            for (i, (_, judge_name, weight)) in judges.iter().enumerate() {
+                let header = if *weight == Decimal::ONE {
+                    judge_name.clone()
+                } else {
+                    format!("{} (x{})", judge_name, weight.normalize())
+                };
+
                 // Set column width should only be done once
                 worksheet.set_column_width(i as u16 + 2, 30)?;
                 worksheet.write_with_format(
                     1 + row_offset,
                     i as u16 + 2,
-                    judge_name,
+                    header,
                     &bold_center_format,
                 )?;
             }
 
-            worksheet.set_column_width(judges.len() as u16 + 2, 20)?;
-            worksheet.set_column_width(judges.len() as u16 + 3, 30)?;
+            worksheet.set_column_width(judges.len() as u16 + 2, 15)?;
+            worksheet.set_column_width(judges.len() as u16 + 3, 20)?;
+            worksheet.set_column_width(judges.len() as u16 + 4, 30)?;
 
             worksheet.write_with_format(
                 1 + row_offset,
                 judges.len() as u16 + 2,
-                "Total Score",
+                "Deductions",
                 &bold_center_format,
             )?;
 
             worksheet.write_with_format(
                 1 + row_offset,
                 judges.len() as u16 + 3,
-                format!("Weighted Score ({:.0}%)", category.weight * 100.0),
+                "Total Score",
+                &bold_center_format,
+            )?;
+
+            worksheet.write_with_format(
+                1 + row_offset,
+                judges.len() as u16 + 4,
+                format!(
+                    "Weighted Score ({}%)",
+                    (category.weight * Decimal::ONE_HUNDRED).normalize()
+                ),
                 &bold_center_format,
             )?;
 
@@ -564,6 +584,9 @@ pub async fn generate_score_spreadsheet(
                 &male_candidates,
                 category,
                 &judges,
+                &conflicts,
+                &criteria_weights,
+                &rounding,
                 3 + row_offset,
                 0,
                 Some(&bold_format),
@@ -579,6 +602,9 @@ pub async fn generate_score_spreadsheet(
                 &female_candidates,
                 category,
                 &judges,
+                &conflicts,
+                &criteria_weights,
+                &rounding,
                 row_offset + 4 + male_candidates.len() as u32,
                 0,
                 Some(&bold_format),
@@ -589,24 +615,99 @@ pub async fn generate_score_spreadsheet(
         }
     }
 
+    // Lets anyone check the scores against the verification endpoint later on
+    let chain_heads = chain::fetch_heads(&pool).await?;
+
+    worksheet.merge_range(
+        row_offset + 1,
+        0,
+        row_offset + 1,
+        6,
+        "Score Chain Head",
+        &heading_format,
+    )?;
+
+    for (i, head) in chain_heads.iter().enumerate() {
+        let row = row_offset + 2 + i as u32;
+
+        worksheet.write(row, 0, head.event_id.to_string())?;
+        worksheet.write(row, 1, &head.head_hash)?;
+        worksheet.write(row, 2, format!("{} entries", head.length))?;
+    }
+
+    // Make it obvious that the results aren't final yet
+    let incomplete_ballots = progress::fetch_incomplete_ballots(&pool, None).await?;
+
+    if !incomplete_ballots.is_empty() {
+        write_incomplete_ballots(workbook.add_worksheet(), &incomplete_ballots)?;
+    }
+
     let workbook_buffer = workbook.save_to_buffer()?;
 
     Ok((http::StatusCode::OK, workbook_buffer))
 }
 
+fn write_incomplete_ballots(
+    worksheet: &mut Worksheet,
+    incomplete_ballots: &[progress::IncompleteBallot],
+) -> Result<(), AppError> {
+    let heading_format = Format::new().set_font_size(13.5).set_bold();
+    let bold_center_format = Format::new().set_bold().set_align(FormatAlign::Center);
+
+    worksheet.set_name("Incomplete Ballots")?;
+    worksheet.set_active(true);
+
+    worksheet.set_column_width(0, 30)?;
+    worksheet.set_column_width(1, 30)?;
+    worksheet.set_column_width(2, 15)?;
+    worksheet.set_column_width(3, 50)?;
+
+    worksheet.merge_range(
+        0,
+        0,
+        0,
+        3,
+        format!(
+            "WARNING: {} incomplete ballot(s), these results are not final",
+            incomplete_ballots.len()
+        )
+        .as_str(),
+        &heading_format,
+    )?;
+
+    worksheet.write_with_format(1, 0, "Category", &bold_center_format)?;
+    worksheet.write_with_format(1, 1, "Judge", &bold_center_format)?;
+    worksheet.write_with_format(1, 2, "Candidate #", &bold_center_format)?;
+    worksheet.write_with_format(1, 3, "Missing Criterias", &bold_center_format)?;
+
+    for (ballot_idx, ballot) in incomplete_ballots.iter().enumerate() {
+        let row = ballot_idx as u32 + 2;
+
+        worksheet.write(row, 0, &ballot.category_name)?;
+        worksheet.write(row, 1, &ballot.judge_name)?;
+        worksheet.write(row, 2, ballot.candidate_number)?;
+        worksheet.write(row, 3, ballot.missing_criterias.join(", "))?;
+    }
+
+    Ok(())
+}
+
 async fn write_scores(
     pool: &PgPool,
     worksheet: &mut Worksheet,
     candidates: &Vec<&Candidate>,
     category: &Category,
-    judges: &Vec<(uuid::Uuid, String)>,
+    judges: &[(uuid::Uuid, String, Decimal)],
+    conflicts: &HashSet<(uuid::Uuid, uuid::Uuid)>,
+    criteria_weights: &HashMap<uuid::Uuid, Decimal>,
+    rounding: &RoundingPolicy,
     row: RowNum,
     col: ColNum,
     format: Option<&Format>,
 ) -> Result<(), AppError> {
-    let mut highest_swimwear: f32 = 0.0;
-    let mut highest_collegiate: f32 = 0.0;
-    let mut highest_formal: f32 = 0.0;
+    let mut highest_swimwear = Decimal::ZERO;
+    let mut highest_collegiate = Decimal::ZERO;
+    let mut highest_formal = Decimal::ZERO;
     let mut swimwear_row: u32 = 0;
     let mut collegiate_row: u32 = 0;
     let mut formal_row: u32 = 0;
@@ -631,13 +732,26 @@ async fn write_scores(
             ),
         )?;
 
-        let mut total_score: f32 = 0.0;
+        let mut total_score = Decimal::ZERO;
+        let mut total_max = Decimal::ZERO;
 
-        // Write candidate scores
-        for (judge_idx, (judge_id, _)) in judges.iter().enumerate() {
-            let judge_total_score: i64 = sqlx::query_scalar(
+        // Same as the tabulation, the judges' totals count by their weight in the panel
+        let weights: Vec<Decimal> = judges.iter().map(|(_, _, weight)| *weight).collect();
+        let factors = tabulation::panel_factors(&weights);
+
+        let mut judge_total_scores: Vec<Option<(Decimal, Decimal)>> =
+            Vec::with_capacity(judges.len());
+
+        // Get candidate scores, judges with a conflict of interest don't count
+        for (judge_id, _, _) in judges.iter() {
+            if conflicts.contains(&(*judge_id, candidate.id)) {
+                judge_total_scores.push(None);
+                continue;
+            }
+
+            let judge_scores = sqlx::query_as::<_, (uuid::Uuid, Decimal, Decimal)>(
                 r#"
-                SELECT COALESCE(SUM(score), 0) as judge_total_score 
+                SELECT criteria_id, score, max
                 FROM scores
                 WHERE candidate_id = ($1) AND category_id = ($2) AND judge_id = ($3)
                 "#,
@@ -645,19 +759,75 @@ async fn write_scores(
             .bind(candidate.id)
             .bind(category.id)
             .bind(judge_id)
-            .fetch_one(pool)
+            .fetch_all(pool)
             .await?;
 
-            total_score += judge_total_score as f32;
+            // Same as the tabulation, weighted criterias count by their weight
+            let judge_total = tabulation::category_total(judge_scores, criteria_weights);
 
-            worksheet.write(
-                row + candidate_idx as u32,
-                col + 2 + judge_idx as u16,
-                judge_total_score as i32,
-            )?;
+            judge_total_scores.push(Some(judge_total));
+        }
+
+        // Same compensation as the tabulation, the average of the rest of the panel
+        let counted: Vec<(Decimal, Decimal)> =
+            judge_total_scores.iter().flatten().copied().collect();
+        let (panel_average, panel_average_max) = if counted.is_empty() {
+            (Decimal::ZERO, Decimal::ZERO)
+        } else {
+            (
+                counted.iter().map(|(score, _)| score).sum::<Decimal>()
+                    / Decimal::from(counted.len()),
+                counted.iter().map(|(_, max)| max).sum::<Decimal>() / Decimal::from(counted.len()),
+            )
+        };
+
+        // Write candidate scores
+        for (judge_idx, judge_total_score) in judge_total_scores.iter().enumerate() {
+            let factor = factors[judge_idx];
+
+            match judge_total_score {
+                Some((judge_total_score, judge_total_max)) => {
+                    total_score += judge_total_score * factor;
+                    total_max += judge_total_max * factor;
+
+                    worksheet.write(
+                        row + candidate_idx as u32,
+                        col + 2 + judge_idx as u16,
+                        rounding
+                            .apply(*judge_total_score)
+                            .to_f64()
+                            .unwrap_or_default(),
+                    )?;
+                }
+                None => {
+                    total_score += panel_average * factor;
+                    total_max += panel_average_max * factor;
+
+                    worksheet.write(
+                        row + candidate_idx as u32,
+                        col + 2 + judge_idx as u16,
+                        format!("{} (COI)", rounding.format(panel_average)),
+                    )?;
+                }
+            }
         }
 
-        let score_in_percentage: f32 = total_score * category.weight;
+        let deduction = sqlx::query_scalar::<_, Decimal>(
+            r#"
+            SELECT COALESCE(SUM(points), 0)
+            FROM deductions
+            WHERE candidate_id = ($1) AND category_id = ($2)
+            "#,
+        )
+        .bind(candidate.id)
+        .bind(category.id)
+        .fetch_one(pool)
+        .await?;
+
+        // Same as the tabulation, taken off after the judges' scores are added up
+        let total_score = tabulation::apply_deduction(total_score, total_max, deduction);
+
+        let score_in_percentage = total_score * category.weight;
 
         match category.name.trim() {
             "University Collegiate Costume" => {
@@ -684,13 +854,19 @@ async fn write_scores(
         worksheet.write(
             row + candidate_idx as u32,
             col + 2 + judges.len() as u16,
-            format!("{:.2}", total_score),
+            deduction.normalize().to_string(),
         )?;
 
         worksheet.write(
             row + candidate_idx as u32,
             col + 3 + judges.len() as u16,
-            format!("{:.2}", score_in_percentage),
+            rounding.format(total_score),
+        )?;
+
+        worksheet.write(
+            row + candidate_idx as u32,
+            col + 4 + judges.len() as u16,
+            rounding.format(score_in_percentage),
         )?;
     }
 
@@ -701,45 +877,44 @@ async fn write_scores(
     Ok(())
 }
 
-// OPTIMIZATION: Do not repeat this huge query since it's already been used like three times
-// already on other functions here
 async fn write_top_ten(
     pool: &PgPool,
     worksheet: &mut Worksheet,
     row: RowNum,
     col: ColNum,
 ) -> Result<(), AppError> {
-    let final_scores = fetch_final_scores(State(pool.to_owned())).await?;
-    let candidates = sqlx::query_as::<_, (String, i32, i32, f32)>(
-        r#"
-        (SELECT CONCAT(last_name, ', ', first_name, ' ', middle_name), candidate_number, gender, final_score
-        FROM candidates
-        WHERE gender = 1
-        ORDER BY final_score DESC
-        LIMIT 5)
-
-        UNION ALL
-
-        (SELECT CONCAT(last_name, ', ', first_name, ' ', middle_name), candidate_number, gender, final_score
-        FROM candidates
-        WHERE gender = 0
-        ORDER BY final_score DESC
-        LIMIT 5)
-        "#,
-    )
-    .fetch_all(pool)
-    .await?;
+    let input = tabulation::load_input(pool).await?;
+    let mut results = tabulation::tabulate(&input);
+    let rounding = RoundingPolicy::from_env();
 
-    let (male_candidates, female_candidates): (
-        Vec<(String, i32, i32, f32)>,
-        Vec<(String, i32, i32, f32)>,
-    ) = candidates
-        .into_iter()
-        .partition(|(_, _, gender, _)| gender.to_owned() == 1);
+    results.sort_by(|a, b| b.final_score.cmp(&a.final_score));
+
+    let top_five = |gender: i32| -> Vec<(String, i32, Decimal)> {
+        results
+            .iter()
+            .filter(|result| result.candidate.gender == gender)
+            .take(5)
+            .map(|result| {
+                let candidate = &result.candidate;
+
+                (
+                    format!(
+                        "{}, {} {}",
+                        candidate.last_name, candidate.first_name, candidate.middle_name
+                    ),
+                    candidate.candidate_number,
+                    result.final_score,
+                )
+            })
+            .collect()
+    };
+
+    let male_candidates = top_five(1);
+    let female_candidates = top_five(0);
 
     worksheet.write(row, 0, "MALE")?;
 
-    for (candidate_idx, (candidate_name, candidate_number, gender, final_score)) in
+    for (candidate_idx, (candidate_name, candidate_number, final_score)) in
         male_candidates.iter().enumerate()
     {
         worksheet.write(
@@ -751,13 +926,13 @@ async fn write_top_ten(
         worksheet.write(
             row + 1 + candidate_idx as u32,
             col + 2,
-            format!("{:.3}", final_score),
+            rounding.format(*final_score),
         )?;
     }
 
     worksheet.write(row + 6, 0, "FEMALE")?;
 
-    for (candidate_idx, (candidate_name, candidate_number, gender, final_score)) in
+    for (candidate_idx, (candidate_name, candidate_number, final_score)) in
         female_candidates.iter().enumerate()
     {
         worksheet.write(
@@ -769,7 +944,7 @@ async fn write_top_ten(
         worksheet.write(
             row + 7 + candidate_idx as u32,
             col + 2,
-            format!("{:.3}", final_score),
+            rounding.format(*final_score),
         )?;
     }
 
@@ -782,109 +957,79 @@ async fn write_by_rank(
     row: RowNum,
     col: ColNum,
 ) -> Result<(), AppError> {
-    let res = sqlx::query_as::<_, CandidateScore>(
-        r#"
-        SELECT 
-            c.id AS candidate_id,
-            c.candidate_number,
-            c.first_name,
-            c.middle_name,
-            c.last_name,
-            c.gender,
-            COALESCE(SUM(s.score), 0) AS total_score, 
-            COALESCE(SUM(s.max), 0) AS total_max,
-            COALESCE(SUM(s.score), 0) * cat.weight AS weighted_score,
-            COALESCE(SUM(s.max), 0) * cat.weight AS weighted_max
-        FROM 
-            candidates c
-        LEFT JOIN 
-            scores s ON s.candidate_id = c.id
-        LEFT JOIN 
-            categories cat ON s.category_id = cat.id
-        GROUP BY
-            c.id, cat.weight
-        ORDER BY 
-            c.candidate_number, c.gender
-        "#,
-    )
-    .fetch_all(pool)
-    .await;
+    let input = tabulation::load_input(pool).await?;
+    let rounding = RoundingPolicy::from_env();
 
-    match res {
-        Ok(candidates) => {
-            let (male_candidates, female_candidates): (Vec<CandidateScore>, Vec<CandidateScore>) =
-                candidates
-                    .into_iter()
-                    .partition(|candidate| candidate.gender == 1);
+    let (male_final_scores, female_final_scores): (Vec<CandidateResult>, Vec<CandidateResult>) =
+        tabulation::tabulate(&input)
+            .into_iter()
+            .partition(|result| result.candidate.gender == 1);
 
-            let male_final_scores = calculate_final_scores(&male_candidates);
-            worksheet.write(row, 0, "MALE")?;
+    worksheet.write(row, 0, "MALE")?;
 
-            for (
-                candidate_idx,
-                (
-                    candidate_id,
-                    (candidate_number, _, first_name, middle_name, last_name, final_score),
-                ),
-            ) in male_final_scores.iter().enumerate()
-            {
-                let candidate_name = format!("{}, {} {}", first_name, middle_name, last_name);
-
-                worksheet.write(
-                    row + 1 + candidate_idx as u32,
-                    col,
-                    candidate_number.to_owned(),
-                )?;
-                worksheet.write(row + 1 + candidate_idx as u32, col + 1, candidate_name)?;
-                worksheet.write(
-                    row + 1 + candidate_idx as u32,
-                    col + 2,
-                    format!("{:.2}", final_score),
-                )?;
-            }
+    for (
+        candidate_idx,
+        CandidateResult {
+            candidate,
+            final_score,
+            ..
+        },
+    ) in male_final_scores.iter().enumerate()
+    {
+        let candidate_name = format!(
+            "{}, {} {}",
+            candidate.first_name, candidate.middle_name, candidate.last_name
+        );
 
-            let female_final_scores = calculate_final_scores(&female_candidates);
-            worksheet.write(row + 1 + male_final_scores.len() as u32, 0, "FEMALE")?;
+        worksheet.write(
+            row + 1 + candidate_idx as u32,
+            col,
+            candidate.candidate_number,
+        )?;
+        worksheet.write(row + 1 + candidate_idx as u32, col + 1, candidate_name)?;
+        worksheet.write(
+            row + 1 + candidate_idx as u32,
+            col + 2,
+            rounding.format(*final_score),
+        )?;
+    }
 
-            for (
-                candidate_idx,
-                (
-                    candidate_id,
-                    (candidate_number, _, first_name, middle_name, last_name, final_score),
-                ),
-            ) in female_final_scores.iter().enumerate()
-            {
-                let candidate_name = format!("{}, {} {}", first_name, middle_name, last_name);
-
-                worksheet.write(
-                    row + 2 + candidate_idx as u32 + male_final_scores.len() as u32,
-                    col,
-                    candidate_number.to_owned(),
-                )?;
-                worksheet.write(
-                    row + 2 + candidate_idx as u32 + male_final_scores.len() as u32,
-                    col + 1,
-                    candidate_name,
-                )?;
-                worksheet.write(
-                    row + 2 + candidate_idx as u32 + male_final_scores.len() as u32,
-                    col + 2,
-                    format!("{:.2}", final_score),
-                )?;
-            }
+    worksheet.write(row + 1 + male_final_scores.len() as u32, 0, "FEMALE")?;
 
-            Ok(())
-        }
-        Err(err) => {
-            eprintln!("Failed to get candidates when computing scores: {err:?}");
+    for (
+        candidate_idx,
+        CandidateResult {
+            candidate,
+            final_score,
+            ..
+        },
+    ) in female_final_scores.iter().enumerate()
+    {
+        let candidate_name = format!(
+            "{}, {} {}",
+            candidate.first_name, candidate.middle_name, candidate.last_name
+        );
 
-            Err(AppError::new(
-                http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to get candidate scores: {}", err),
-            ))
-        }
+        worksheet.write(
+            row + 2 + candidate_idx as u32 + male_final_scores.len() as u32,
+            col,
+            candidate.candidate_number,
+        )?;
+        worksheet.write(
+            row + 2 + candidate_idx as u32 + male_final_scores.len() as u32,
+            col + 1,
+            candidate_name,
+        )?;
+        worksheet.write(
+            row + 2 + candidate_idx as u32 + male_final_scores.len() as u32,
+            col + 2,
+            rounding.format(*final_score),
+        )?;
     }
+
+    Ok(())
 }
+
 // OLD CODE
 // FOR GENERATING CSV SPREADSHEET
 
@@ -906,18 +1051,21 @@ pub async fn generate_csv(
         "Candidate Middle Name",
         "Candidate Last Name",
         "Judge",
+        "Judge Weight",
         "Score",
         "Max",
         "Weight",
+        // The candidate's total for the category, only on its first row
+        "Category Deductions",
     ];
 
     csv_writer.write_record(&headers).map_err(|err| {
-        AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to write record for headers: {}", err),
-        )
+        AppError::internal(format!("Failed to write record for headers: {}", err))
     })?;
 
+    // Candidates whose category deductions were already written
+    let mut deducted: HashSet<(uuid::Uuid, uuid::Uuid)> = HashSet::new();
+
     for category in categories.iter() {
         let criterias = sqlx::query_as::<_, CriteriaIdName>(
             "SELECT id, name FROM criterias WHERE category_id = ($1)",
@@ -929,18 +1077,26 @@ pub async fn generate_csv(
         for criteria in criterias.iter() {
             let scores = sqlx::query_as::<_, CriteriaScore>(
                 r#"
-                SELECT s.score, s.max, j.name as judge_name,
+                SELECT s.score, s.max, s.candidate_id, j.name as judge_name,
                     can.first_name as candidate_first_name,
                     can.middle_name as candidate_middle_name,
                     can.last_name as candidate_last_name,
                     cat.weight as weight,
-                    e.name as event_name
+                    COALESCE((
+                        SELECT SUM(d.points)
+                        FROM deductions d
+                        WHERE d.candidate_id = s.candidate_id AND d.category_id = s.category_id
+                    ), 0) as deductions,
+                    e.name as event_name,
+                    COALESCE(a.weight, j.weight) as judge_weight
                 FROM scores s
                 JOIN judges j ON j.id = s.judge_id
+                LEFT JOIN judge_category_assignments a
+                    ON a.judge_id = s.judge_id AND a.category_id = s.category_id
                 JOIN candidates can ON can.id = s.candidate_id
                 JOIN categories cat ON cat.id = s.category_id
                 JOIN events e ON e.id = cat.event_id
-                WHERE s.category_id = ($1) AND s.criteria_id = ($2)
+                WHERE s.category_id = ($1) AND s.criteria_id = ($2) AND j.score_exclusion = FALSE
                 "#,
             )
             .bind(category.id)
@@ -949,6 +1105,12 @@ pub async fn generate_csv(
             .await?;
 
             for score in scores.iter() {
+                let deductions = if deducted.insert((score.candidate_id, category.id)) {
+                    score.deductions.normalize().to_string()
+                } else {
+                    String::new()
+                };
+
                 csv_writer
                     .write_record(vec![
                         &score.event_name,
@@ -958,26 +1120,22 @@ pub async fn generate_csv(
                         &score.candidate_middle_name,
                         &score.candidate_last_name,
                         &score.judge_name,
-                        &score.score.to_string(),
-                        &score.max.to_string(),
-                        &score.weight.to_string(),
+                        &score.judge_weight.normalize().to_string(),
+                        &score.score.normalize().to_string(),
+                        &score.max.normalize().to_string(),
+                        &score.weight.normalize().to_string(),
+                        &deductions,
                     ])
                     .map_err(|err| {
-                        AppError::new(
-                            http::StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Failed to serialize record: {}", err),
-                        )
+                        AppError::internal(format!("Failed to serialize record: {}", err))
                     })?;
             }
         }
     }
 
-    let csv_bytes = csv_writer.into_inner().map_err(|err| {
-        AppError::new(
-            http::StatusCode::INTERNAL_SERVER_ERROR,
-            format! {"Failed to generate CSV file: {}", err},
-        )
-    })?;
+    let csv_bytes = csv_writer
+        .into_inner()
+        .map_err(|err| AppError::internal(format! {"Failed to generate CSV file: {}", err}))?;
 
     Ok((http::StatusCode::OK, csv_bytes))
 }
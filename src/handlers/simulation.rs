@@ -0,0 +1,123 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::extract::State;
+use axum::response::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::error::AppError;
+
+use super::tabulation::{self, Method, RoundingPolicy, TabulationInput};
+
+// Changes to try out, nothing here is written to the database
+#[derive(Debug, Default, Deserialize)]
+pub struct Overrides {
+    #[serde(default)]
+    pub excluded_judges: Vec<uuid::Uuid>,
+    // category_id -> weight, e.g. 0.25 for 25%
+    #[serde(default)]
+    pub category_weights: HashMap<uuid::Uuid, Decimal>,
+    #[serde(default)]
+    pub method: Method,
+    #[serde(default)]
+    pub dropped_criterias: Vec<uuid::Uuid>,
+}
+
+impl Overrides {
+    pub fn apply(&self, input: &TabulationInput) -> TabulationInput {
+        let excluded: HashSet<&uuid::Uuid> = self.excluded_judges.iter().collect();
+        let dropped: HashSet<&uuid::Uuid> = self.dropped_criterias.iter().collect();
+
+        let mut input = input.clone();
+
+        input.judges.retain(|judge| !excluded.contains(&judge.id));
+        input.scores.retain(|score| {
+            !excluded.contains(&score.judge_id) && !dropped.contains(&score.criteria_id)
+        });
+
+        for category in input.categories.iter_mut() {
+            if let Some(weight) = self.category_weights.get(&category.id) {
+                category.weight = *weight;
+            }
+        }
+
+        input
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulatedResult {
+    pub candidate_id: uuid::Uuid,
+    pub candidate_number: i32,
+    pub first_name: String,
+    pub middle_name: String,
+    pub last_name: String,
+    pub gender: i32,
+    pub official_score: Decimal,
+    pub official_rank: u32,
+    pub simulated_score: Decimal,
+    pub simulated_rank: u32,
+    // Positive when the candidate moves up
+    pub rank_delta: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Simulation {
+    pub method: Method,
+    pub results: Vec<SimulatedResult>,
+}
+
+pub fn simulate(
+    input: &TabulationInput,
+    overrides: &Overrides,
+    rounding: &RoundingPolicy,
+) -> Simulation {
+    let official: HashMap<uuid::Uuid, (Decimal, u32)> = tabulation::tabulate(input)
+        .into_iter()
+        .map(|result| (result.candidate.id, (result.final_score, result.rank)))
+        .collect();
+
+    let results = tabulation::tabulate_with(&overrides.apply(input), overrides.method)
+        .into_iter()
+        .map(|result| {
+            let (official_score, official_rank) = official
+                .get(&result.candidate.id)
+                .copied()
+                .unwrap_or_default();
+
+            SimulatedResult {
+                candidate_id: result.candidate.id,
+                candidate_number: result.candidate.candidate_number,
+                first_name: result.candidate.first_name,
+                middle_name: result.candidate.middle_name,
+                last_name: result.candidate.last_name,
+                gender: result.candidate.gender,
+                official_score: rounding.apply(official_score),
+                official_rank,
+                simulated_score: rounding.apply(result.final_score),
+                simulated_rank: result.rank,
+                rank_delta: official_rank as i64 - result.rank as i64,
+            }
+        })
+        .collect();
+
+    Simulation {
+        method: overrides.method,
+        results,
+    }
+}
+
+// What the results would be with the given overrides, next to the official ones
+pub async fn simulate_results(
+    State(pool): State<PgPool>,
+    axum::Json(overrides): axum::Json<Overrides>,
+) -> Result<axum::Json<Simulation>, AppError> {
+    let input = tabulation::load_input(&pool).await?;
+
+    Ok(axum::Json(simulate(
+        &input,
+        &overrides,
+        &RoundingPolicy::from_env(),
+    )))
+}
@@ -0,0 +1,791 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::error::AppError;
+
+use super::analytics::ranks;
+use super::assignment;
+use super::category::Category;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct TabulationCandidate {
+    pub id: uuid::Uuid,
+    pub candidate_number: i32,
+    pub first_name: String,
+    pub middle_name: String,
+    pub last_name: String,
+    pub gender: i32,
+    // Of the candidate's category
+    pub event_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct TabulationJudge {
+    pub id: uuid::Uuid,
+    pub event_id: uuid::Uuid,
+    pub score_exclusion: bool,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ScoreRow {
+    pub candidate_id: uuid::Uuid,
+    pub category_id: uuid::Uuid,
+    pub criteria_id: uuid::Uuid,
+    pub judge_id: uuid::Uuid,
+    pub score: Decimal,
+    pub max: Decimal,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct TabulationCriteria {
+    pub id: uuid::Uuid,
+    pub category_id: uuid::Uuid,
+    pub weight: Option<Decimal>,
+}
+
+// Everything the tabulation needs, loaded once so the computation itself stays pure
+#[derive(Debug, Clone)]
+pub struct TabulationInput {
+    pub candidates: Vec<TabulationCandidate>,
+    pub categories: Vec<Category>,
+    pub criterias: Vec<TabulationCriteria>,
+    // Excluded judges too, their scores are left out by the tabulation itself
+    pub judges: Vec<TabulationJudge>,
+    pub scores: Vec<ScoreRow>,
+    // (judge_id, candidate_id) pairs where the judge is affiliated with the candidate's college
+    pub conflicts: HashSet<(uuid::Uuid, uuid::Uuid)>,
+    // (candidate_id, category_id) -> total points deducted
+    pub deductions: HashMap<(uuid::Uuid, uuid::Uuid), Decimal>,
+    // category_id -> judges on its panel, categories without one are scored by every judge
+    pub assignments: HashMap<uuid::Uuid, HashSet<uuid::Uuid>>,
+    // (judge_id, category_id) -> weight of the judge in the category, only the ones that aren't 1
+    pub judge_weights: HashMap<(uuid::Uuid, uuid::Uuid), Decimal>,
+}
+
+impl TabulationInput {
+    // Leaves out everything of the other events, so their candidates are neither ranked nor
+    // written when only this event's results are asked for
+    pub fn for_event(mut self, event_id: uuid::Uuid) -> Self {
+        self.candidates
+            .retain(|candidate| candidate.event_id == Some(event_id));
+        self.categories
+            .retain(|category| category.event_id == event_id);
+
+        let category_ids: HashSet<uuid::Uuid> =
+            self.categories.iter().map(|category| category.id).collect();
+
+        self.criterias
+            .retain(|criteria| category_ids.contains(&criteria.category_id));
+        self.judges.retain(|judge| judge.event_id == event_id);
+        self.scores
+            .retain(|score| category_ids.contains(&score.category_id));
+
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    // Weighted sum of the scores over the weighted sum of the max scores, what we always used
+    #[default]
+    WeightedPercentage,
+    // Every judge ranks the candidates per category, the final score is the weighted average
+    // of those ranks so lower is better
+    AverageRank,
+}
+
+impl Method {
+    pub fn higher_is_better(&self) -> bool {
+        *self == Method::WeightedPercentage
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    // 85.3335 -> 85.334
+    #[default]
+    HalfUp,
+    // Ties go to the even digit, 85.3335 -> 85.334 but 85.3345 -> 85.334
+    HalfEven,
+    // Extra digits are dropped, 85.3339 -> 85.333
+    Truncate,
+}
+
+// The one place final scores get rounded, everything shown to people goes through here so the
+// API, the spreadsheet and the snapshots always agree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RoundingPolicy {
+    pub precision: u32,
+    pub mode: RoundingMode,
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> Self {
+        Self {
+            precision: 3,
+            mode: RoundingMode::default(),
+        }
+    }
+}
+
+impl RoundingPolicy {
+    // Configured with RESULTS_PRECISION and RESULTS_ROUNDING (half_up, half_even or truncate)
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        let precision = env::var("RESULTS_PRECISION")
+            .ok()
+            .and_then(|precision| precision.parse().ok())
+            .unwrap_or(default.precision);
+
+        let mode = match env::var("RESULTS_ROUNDING").as_deref() {
+            Ok("half_up") => RoundingMode::HalfUp,
+            Ok("half_even") => RoundingMode::HalfEven,
+            Ok("truncate") => RoundingMode::Truncate,
+            Ok(mode) => {
+                eprintln!(
+                    "Unknown RESULTS_ROUNDING {mode:?}, using {:?}",
+                    default.mode
+                );
+
+                default.mode
+            }
+            Err(_) => default.mode,
+        };
+
+        Self { precision, mode }
+    }
+
+    pub fn apply(&self, value: Decimal) -> Decimal {
+        let strategy = match self.mode {
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::Truncate => RoundingStrategy::ToZero,
+        };
+
+        value.round_dp_with_strategy(self.precision, strategy)
+    }
+
+    // Always shows every digit of the precision, e.g. 85.300
+    pub fn format(&self, value: Decimal) -> String {
+        format!("{:.*}", self.precision as usize, self.apply(value))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CandidateResult {
+    pub candidate: TabulationCandidate,
+    // Unrounded, apply a RoundingPolicy before showing it to anyone
+    pub final_score: Decimal,
+    // Placement among the candidates of the same gender, ties share a rank
+    pub rank: u32,
+}
+
+pub async fn load_input(pool: &PgPool) -> Result<TabulationInput, AppError> {
+    let candidates = sqlx::query_as::<_, TabulationCandidate>(
+        r#"
+        SELECT c.id, c.candidate_number, c.first_name, c.middle_name, c.last_name, c.gender,
+            cat.event_id
+        FROM candidates c
+        LEFT JOIN categories cat ON cat.id = c.category_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let categories = sqlx::query_as::<_, Category>("SELECT * FROM categories")
+        .fetch_all(pool)
+        .await?;
+
+    let criterias = fetch_criterias(pool).await?;
+
+    let judges =
+        sqlx::query_as::<_, TabulationJudge>("SELECT id, event_id, score_exclusion FROM judges")
+            .fetch_all(pool)
+            .await?;
+
+    let scores = sqlx::query_as::<_, ScoreRow>(
+        "SELECT candidate_id, category_id, criteria_id, judge_id, score, max FROM scores",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let conflicts = fetch_conflicts(pool).await?;
+
+    let deductions = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid, Decimal)>(
+        r#"
+        SELECT candidate_id, category_id, SUM(points)
+        FROM deductions
+        GROUP BY candidate_id, category_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(candidate_id, category_id, points)| ((candidate_id, category_id), points))
+    .collect();
+
+    // The weight of an assignment wins over the weight of the judge
+    let judge_weights = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid, Decimal)>(
+        r#"
+        SELECT j.id, c.id, COALESCE(a.weight, j.weight)
+        FROM judges j
+        JOIN categories c ON c.event_id = j.event_id
+        LEFT JOIN judge_category_assignments a ON a.judge_id = j.id AND a.category_id = c.id
+        WHERE COALESCE(a.weight, j.weight) <> 1
+        "#,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(judge_id, category_id, weight)| ((judge_id, category_id), weight))
+    .collect();
+
+    Ok(TabulationInput {
+        candidates,
+        categories,
+        criterias,
+        judges,
+        scores,
+        conflicts,
+        deductions,
+        assignments: assignment::fetch_assignments(pool).await?,
+        judge_weights,
+    })
+}
+
+pub async fn fetch_criterias(pool: &PgPool) -> Result<Vec<TabulationCriteria>, AppError> {
+    let criterias =
+        sqlx::query_as::<_, TabulationCriteria>("SELECT id, category_id, weight FROM criterias")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(criterias)
+}
+
+// Criteria weights only count when every criteria of the category has one, otherwise the
+// category keeps weighting its criterias by their max scores
+pub fn criteria_weights(criterias: &[TabulationCriteria]) -> HashMap<uuid::Uuid, Decimal> {
+    let mut fully_weighted: HashMap<uuid::Uuid, bool> = HashMap::new();
+
+    for criteria in criterias.iter() {
+        *fully_weighted.entry(criteria.category_id).or_insert(true) &= criteria.weight.is_some();
+    }
+
+    criterias
+        .iter()
+        .filter(|criteria| fully_weighted.get(&criteria.category_id) == Some(&true))
+        .filter_map(|criteria| criteria.weight.map(|weight| (criteria.id, weight)))
+        .collect()
+}
+
+// (criteria_id, score, max)
+pub type CriteriaScore = (uuid::Uuid, Decimal, Decimal);
+
+// Adds up (criteria_id, score, max) entries of a single category into (score, max). Weighted
+// criterias count by their percentage times their weight, which is then scaled back to the max
+// so the category still lines up with the ones weighted by max score.
+pub fn category_total(
+    scores: impl IntoIterator<Item = CriteriaScore>,
+    weights: &HashMap<uuid::Uuid, Decimal>,
+) -> (Decimal, Decimal) {
+    // criteria_id -> (score sum, max sum)
+    let mut criterias: HashMap<uuid::Uuid, (Decimal, Decimal)> = HashMap::new();
+
+    for (criteria_id, score, max) in scores {
+        let (score_sum, max_sum) = criterias
+            .entry(criteria_id)
+            .or_insert((Decimal::ZERO, Decimal::ZERO));
+
+        *score_sum += score;
+        *max_sum += max;
+    }
+
+    let score_sum: Decimal = criterias.values().map(|(score, _)| *score).sum();
+    let max_sum: Decimal = criterias.values().map(|(_, max)| *max).sum();
+
+    if !criterias
+        .keys()
+        .any(|criteria_id| weights.contains_key(criteria_id))
+    {
+        return (score_sum, max_sum);
+    }
+
+    let mut weighted = Decimal::ZERO;
+    let mut weight_sum = Decimal::ZERO;
+
+    for (criteria_id, (score, max)) in criterias {
+        let Some(weight) = weights.get(&criteria_id) else {
+            continue;
+        };
+
+        if max > Decimal::ZERO {
+            weighted += score / max * weight;
+            weight_sum += weight;
+        }
+    }
+
+    if weight_sum == Decimal::ZERO {
+        return (Decimal::ZERO, max_sum);
+    }
+
+    (max_sum * weighted / weight_sum, max_sum)
+}
+
+// Weights are relative to the rest of the panel, so they're scaled to add up to the number of
+// judges. That way totals stay out of the same max as before and equal weights change nothing.
+pub fn panel_factors(weights: &[Decimal]) -> Vec<Decimal> {
+    let weight_sum: Decimal = weights.iter().sum();
+
+    if weight_sum <= Decimal::ZERO {
+        return vec![Decimal::ONE; weights.len()];
+    }
+
+    weights
+        .iter()
+        .map(|weight| weight * Decimal::from(weights.len()) / weight_sum)
+        .collect()
+}
+
+// (judge_id, category_id) -> factor the judge's scores in the category are multiplied by
+fn judge_factors(input: &TabulationInput) -> HashMap<(uuid::Uuid, uuid::Uuid), Decimal> {
+    let mut factors = HashMap::new();
+
+    for category in input.categories.iter() {
+        let panel: Vec<uuid::Uuid> = input
+            .judges
+            .iter()
+            .filter(|judge| assignment::on_panel(&input.assignments, judge, category))
+            .map(|judge| judge.id)
+            .collect();
+
+        let weights: Vec<Decimal> = panel
+            .iter()
+            .map(|judge_id| {
+                input
+                    .judge_weights
+                    .get(&(*judge_id, category.id))
+                    .copied()
+                    .unwrap_or(Decimal::ONE)
+            })
+            .collect();
+
+        for (judge_id, factor) in panel.into_iter().zip(panel_factors(&weights)) {
+            factors.insert((judge_id, category.id), factor);
+        }
+    }
+
+    factors
+}
+
+// Deductions come off after the judges' scores are added up. The points are out of 100 of the
+// category, so they cost the same no matter how many judges or criterias there are.
+pub fn apply_deduction(score: Decimal, max: Decimal, points: Decimal) -> Decimal {
+    (score - max * points / Decimal::ONE_HUNDRED).max(Decimal::ZERO)
+}
+
+// (candidate_id, category_id) -> (score, max) of every candidate in every category they got
+// scored in
+fn candidate_category_totals(
+    input: &TabulationInput,
+) -> HashMap<(uuid::Uuid, uuid::Uuid), (Decimal, Decimal)> {
+    let weights = criteria_weights(&input.criterias);
+    let factors = judge_factors(input);
+
+    let mut scores: HashMap<(uuid::Uuid, uuid::Uuid), Vec<CriteriaScore>> = HashMap::new();
+
+    for score in effective_scores(input) {
+        let factor = factors
+            .get(&(score.judge_id, score.category_id))
+            .copied()
+            .unwrap_or(Decimal::ONE);
+
+        scores
+            .entry((score.candidate_id, score.category_id))
+            .or_default()
+            .push((score.criteria_id, score.score * factor, score.max * factor));
+    }
+
+    scores
+        .into_iter()
+        .map(|(key, scores)| {
+            let (score, max) = category_total(scores, &weights);
+            let points = input.deductions.get(&key).copied().unwrap_or_default();
+
+            (key, (apply_deduction(score, max, points), max))
+        })
+        .collect()
+}
+
+pub async fn fetch_conflicts(pool: &PgPool) -> Result<HashSet<(uuid::Uuid, uuid::Uuid)>, AppError> {
+    let conflicts = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid)>(
+        r#"
+        SELECT a.judge_id, c.id
+        FROM judge_college_affiliations a
+        JOIN candidates c ON c.college_id = a.college_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(conflicts.into_iter().collect())
+}
+
+// (judge_id, category_id) of every judge on the panel of every category
+fn panels(input: &TabulationInput) -> HashSet<(uuid::Uuid, uuid::Uuid)> {
+    input
+        .categories
+        .iter()
+        .flat_map(|category| {
+            input
+                .judges
+                .iter()
+                .filter(|judge| assignment::on_panel(&input.assignments, judge, category))
+                .map(|judge| (judge.id, category.id))
+        })
+        .collect()
+}
+
+// Scores that count towards the results, only the ones of judges on the category's panel.
+// Scores of a judge with a conflict of interest are dropped and replaced with the average of
+// the rest of the panel for that candidate, so the candidate is not penalized by the missing
+// score.
+pub fn effective_scores(input: &TabulationInput) -> Vec<ScoreRow> {
+    let panels = panels(input);
+
+    let mut scores: Vec<ScoreRow> = input
+        .scores
+        .iter()
+        .filter(|score| {
+            panels.contains(&(score.judge_id, score.category_id))
+                && !input
+                    .conflicts
+                    .contains(&(score.judge_id, score.candidate_id))
+        })
+        .cloned()
+        .collect();
+
+    let mut imputed = Vec::new();
+
+    for (judge_id, candidate_id) in input.conflicts.iter() {
+        let Some(judge) = input.judges.iter().find(|judge| judge.id == *judge_id) else {
+            continue;
+        };
+
+        // An affiliation only matters for the candidates of the judge's own event
+        if !input.candidates.iter().any(|candidate| {
+            candidate.id == *candidate_id && candidate.event_id == Some(judge.event_id)
+        }) {
+            continue;
+        }
+
+        // Only the categories the judge would have scored
+        for category in input
+            .categories
+            .iter()
+            .filter(|category| panels.contains(&(judge.id, category.id)))
+        {
+            // criteria_id -> (score sum, max, count)
+            let mut criterias: HashMap<uuid::Uuid, (Decimal, Decimal, usize)> = HashMap::new();
+
+            for score in scores
+                .iter()
+                .filter(|s| s.candidate_id == *candidate_id && s.category_id == category.id)
+            {
+                let (sum, max, count) =
+                    criterias
+                        .entry(score.criteria_id)
+                        .or_insert((Decimal::ZERO, Decimal::ZERO, 0));
+
+                *sum += score.score;
+                *max = score.max;
+                *count += 1;
+            }
+
+            for (criteria_id, (sum, max, count)) in criterias {
+                imputed.push(ScoreRow {
+                    candidate_id: *candidate_id,
+                    category_id: category.id,
+                    criteria_id,
+                    judge_id: *judge_id,
+                    score: sum / Decimal::from(count),
+                    max,
+                });
+            }
+        }
+    }
+
+    scores.extend(imputed);
+
+    scores
+}
+
+pub fn tabulate(input: &TabulationInput) -> Vec<CandidateResult> {
+    tabulate_with(input, Method::default())
+}
+
+pub fn tabulate_with(input: &TabulationInput, method: Method) -> Vec<CandidateResult> {
+    let final_scores = match method {
+        Method::WeightedPercentage => weighted_percentage(input),
+        Method::AverageRank => average_rank(input),
+    };
+
+    let mut results: Vec<CandidateResult> = input
+        .candidates
+        .iter()
+        .map(|candidate| CandidateResult {
+            candidate: candidate.clone(),
+            final_score: final_scores
+                .get(&candidate.id)
+                .copied()
+                .unwrap_or(Decimal::ZERO),
+            rank: 0,
+        })
+        .collect();
+
+    assign_ranks(&mut results, method);
+
+    // Males first, then by candidate number
+    results.sort_by_key(|result| {
+        (
+            result.candidate.gender != 1,
+            result.candidate.candidate_number,
+        )
+    });
+
+    results
+}
+
+// Ranks come from the unrounded scores, two candidates can show the same rounded score and
+// still be placed differently
+fn assign_ranks(results: &mut [CandidateResult], method: Method) {
+    let mut genders: Vec<i32> = results
+        .iter()
+        .map(|result| result.candidate.gender)
+        .collect();
+    genders.sort();
+    genders.dedup();
+
+    for gender in genders {
+        let mut scores: Vec<Decimal> = results
+            .iter()
+            .filter(|result| result.candidate.gender == gender)
+            .map(|result| result.final_score)
+            .collect();
+
+        if method.higher_is_better() {
+            scores.sort_by(|a, b| b.cmp(a));
+        } else {
+            scores.sort();
+        }
+
+        for result in results
+            .iter_mut()
+            .filter(|result| result.candidate.gender == gender)
+        {
+            // 1 + the number of candidates that did strictly better
+            let better = scores
+                .iter()
+                .take_while(|score| **score != result.final_score)
+                .count();
+
+            result.rank = better as u32 + 1;
+        }
+    }
+}
+
+fn category_weights(input: &TabulationInput) -> HashMap<uuid::Uuid, Decimal> {
+    input
+        .categories
+        .iter()
+        .map(|category| (category.id, category.weight))
+        .collect()
+}
+
+// The final score of a candidate is the weighted sum of their scores over the weighted sum
+// of the max scores, across every category
+fn weighted_percentage(input: &TabulationInput) -> HashMap<uuid::Uuid, Decimal> {
+    let weights = category_weights(input);
+
+    let mut weighted: HashMap<uuid::Uuid, (Decimal, Decimal)> = HashMap::new();
+
+    for ((candidate_id, category_id), (score_sum, max_sum)) in candidate_category_totals(input) {
+        let weight = weights.get(&category_id).copied().unwrap_or(Decimal::ZERO);
+        let (weighted_scores_sum, weighted_max_sum) = weighted
+            .entry(candidate_id)
+            .or_insert((Decimal::ZERO, Decimal::ZERO));
+
+        *weighted_scores_sum += score_sum * weight;
+        *weighted_max_sum += max_sum * weight;
+    }
+
+    weighted
+        .into_iter()
+        .map(|(candidate_id, (score, max))| {
+            let final_score = if max > Decimal::ZERO {
+                score / max * Decimal::ONE_HUNDRED
+            } else {
+                Decimal::ZERO
+            };
+
+            (candidate_id, final_score)
+        })
+        .collect()
+}
+
+fn average_rank(input: &TabulationInput) -> HashMap<uuid::Uuid, Decimal> {
+    let weights = category_weights(input);
+    let genders: HashMap<uuid::Uuid, i32> = input
+        .candidates
+        .iter()
+        .map(|candidate| (candidate.id, candidate.gender))
+        .collect();
+
+    let criteria_weights = criteria_weights(&input.criterias);
+    let factors = judge_factors(input);
+
+    // (category_id, judge_id, gender) -> candidate_id -> scores
+    let mut ballots: HashMap<
+        (uuid::Uuid, uuid::Uuid, i32),
+        HashMap<uuid::Uuid, Vec<CriteriaScore>>,
+    > = HashMap::new();
+
+    for score in effective_scores(input) {
+        let Some(gender) = genders.get(&score.candidate_id) else {
+            continue;
+        };
+
+        ballots
+            .entry((score.category_id, score.judge_id, *gender))
+            .or_default()
+            .entry(score.candidate_id)
+            .or_default()
+            .push((score.criteria_id, score.score, score.max));
+    }
+
+    // (candidate_id, category_id) -> (rank, factor) given by each judge
+    let mut category_ranks: HashMap<(uuid::Uuid, uuid::Uuid), Vec<(Decimal, Decimal)>> =
+        HashMap::new();
+
+    for ((category_id, judge_id, _), ballot) in ballots {
+        let factor = factors
+            .get(&(judge_id, category_id))
+            .copied()
+            .unwrap_or(Decimal::ONE);
+
+        let (candidate_ids, scores): (Vec<uuid::Uuid>, Vec<Decimal>) = ballot
+            .into_iter()
+            .map(|(candidate_id, scores)| {
+                let (score, max) = category_total(scores, &criteria_weights);
+                let points = input
+                    .deductions
+                    .get(&(candidate_id, category_id))
+                    .copied()
+                    .unwrap_or_default();
+
+                (candidate_id, apply_deduction(score, max, points))
+            })
+            .unzip();
+
+        for (candidate_id, rank) in candidate_ids.into_iter().zip(ranks(&scores)) {
+            category_ranks
+                .entry((candidate_id, category_id))
+                .or_default()
+                // Ranks are always whole or half numbers so this is exact
+                .push((Decimal::from_f64(rank).unwrap_or_default(), factor));
+        }
+    }
+
+    // candidate_id -> (weighted rank sum, weight sum)
+    let mut weighted: HashMap<uuid::Uuid, (Decimal, Decimal)> = HashMap::new();
+
+    for ((candidate_id, category_id), ranks) in category_ranks {
+        let weight = weights.get(&category_id).copied().unwrap_or(Decimal::ZERO);
+        // Weighted by the judges' factors, a plain average when every judge counts the same
+        let average = ranks
+            .iter()
+            .map(|(rank, factor)| rank * factor)
+            .sum::<Decimal>()
+            / ranks.iter().map(|(_, factor)| factor).sum::<Decimal>();
+        let (rank_sum, weight_sum) = weighted
+            .entry(candidate_id)
+            .or_insert((Decimal::ZERO, Decimal::ZERO));
+
+        *rank_sum += average * weight;
+        *weight_sum += weight;
+    }
+
+    weighted
+        .into_iter()
+        .filter(|(_, (_, weight_sum))| *weight_sum > Decimal::ZERO)
+        .map(|(candidate_id, (rank_sum, weight_sum))| (candidate_id, rank_sum / weight_sum))
+        .collect()
+}
+
+// How a candidate did in a single category, before the category weights are applied
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryBreakdown {
+    pub candidate_id: uuid::Uuid,
+    pub category_id: uuid::Uuid,
+    pub category_name: String,
+    pub weight: Decimal,
+    pub score: Decimal,
+    pub max: Decimal,
+    pub percentage: Decimal,
+    // Already taken off the score, left out of snapshots made before deductions existed
+    #[serde(default, skip_serializing_if = "Decimal::is_zero")]
+    pub deduction: Decimal,
+}
+
+pub fn category_breakdown(input: &TabulationInput) -> Vec<CategoryBreakdown> {
+    let totals = candidate_category_totals(input);
+
+    let candidate_numbers: HashMap<uuid::Uuid, i32> = input
+        .candidates
+        .iter()
+        .map(|candidate| (candidate.id, candidate.candidate_number))
+        .collect();
+
+    let mut breakdown: Vec<CategoryBreakdown> = totals
+        .into_iter()
+        .filter_map(|((candidate_id, category_id), (score, max))| {
+            let category = input.categories.iter().find(|c| c.id == category_id)?;
+
+            Some(CategoryBreakdown {
+                candidate_id,
+                category_id,
+                category_name: category.name.clone(),
+                weight: category.weight,
+                score,
+                max,
+                percentage: if max > Decimal::ZERO {
+                    score / max * Decimal::ONE_HUNDRED
+                } else {
+                    Decimal::ZERO
+                },
+                deduction: input
+                    .deductions
+                    .get(&(candidate_id, category_id))
+                    .copied()
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    breakdown.sort_by_key(|row| {
+        (
+            row.category_name.clone(),
+            candidate_numbers.get(&row.candidate_id).copied(),
+            row.candidate_id,
+        )
+    });
+
+    breakdown
+}
@@ -1,8 +1,1085 @@
 #[cfg(test)]
-
 use super::*;
 
 #[test]
-pub fn connection_test() {
+pub fn connection_test() {}
+
+#[test]
+pub fn subscription_filters_test() {
+    use crate::realtime::subscription::{Access, Role, Subscription, Topic};
+
+    let event_id = uuid::Uuid::from_u128(1);
+    let other_event_id = uuid::Uuid::from_u128(2);
+
+    let published = Topic::from_payload(&format!(
+        r#"{{"type": "results_published", "event_id": "{event_id}"}}"#
+    ));
+    let score = Topic::from_payload(&format!(
+        r#"{{"table": "scores", "data": {{"category_id": "{}"}}}}"#,
+        uuid::Uuid::from_u128(3)
+    ));
+    let other_event = Topic::from_payload(&format!(
+        r#"{{"type": "category_activated", "event_id": "{other_event_id}"}}"#
+    ));
+
+    let audience = Subscription {
+        role: Role::Audience,
+        ..Default::default()
+    };
+    assert!(audience.matches(&published));
+    assert!(!audience.matches(&score));
+
+    let judge = Subscription {
+        role: Role::Judge,
+        event_id: Some(event_id),
+        ..Default::default()
+    };
+    assert!(judge.matches(&published));
+    assert!(!judge.matches(&score));
+    assert!(!judge.matches(&other_event));
+
+    // Every role has to know when it missed something and should refetch
+    let resync = Topic::from_payload(&crate::realtime::resync_message("listener_lost", 0));
+    assert!(audience.matches(&resync));
+    assert!(judge.matches(&resync));
+
+    let tabulator = Subscription {
+        role: Role::Tabulator,
+        ..Default::default()
+    };
+    assert!(tabulator.matches(&published));
+    assert!(tabulator.matches(&score));
+    assert!(tabulator.matches(&other_event));
+
+    // Asking for a role doesn't grant it
+    assert_eq!(Subscription::default().role, Role::Audience);
+    assert_eq!(
+        tabulator.clone().restrict(Access::Audience).role,
+        Role::Audience
+    );
+    assert_eq!(
+        tabulator.clone().restrict(Access::Tabulator).role,
+        Role::Tabulator
+    );
+
+    // An authenticated judge is held to their own event whatever they subscribe to
+    let judge_id = uuid::Uuid::from_u128(10);
+    let authenticated = tabulator.restrict(Access::Judge { event_id, judge_id });
+    assert_eq!(authenticated.role, Role::Judge);
+    assert_eq!(authenticated.judge_id, Some(judge_id));
+    assert!(!authenticated.matches(&other_event));
+}
+
+#[test]
+pub fn history_replay_test() {
+    use crate::realtime::history::History;
+
+    let mut history = History::new(3);
+
+    for i in 0..5 {
+        history.record(&format!(r#"{{"type": "test", "i": {i}}}"#));
+    }
+
+    // Only 3, 4 and 5 are still buffered
+    let replay = history.since(3);
+    assert!(!replay.gap);
+    assert_eq!(replay.events.len(), 2);
+    assert!(replay.events[0].contains(r#""seq":4"#));
+
+    assert!(history.since(1).gap);
+    assert!(!history.since(5).gap);
+    assert!(history.since(10).gap);
+}
+
+#[test]
+pub fn progress_matrix_test() {
+    use std::collections::HashSet;
+
+    use rust_decimal::Decimal;
+
+    use super::category::Category;
+    use super::progress::{compute_progress, ProgressCandidate, ProgressCriteria, ProgressJudge};
+
+    let id = uuid::Uuid::from_u128;
+    let category = Category {
+        id: id(1),
+        name: "Swimwear".to_string(),
+        weight: Decimal::new(3, 1),
+        event_id: id(2),
+    };
+    let judges = vec![
+        ProgressJudge {
+            id: id(10),
+            name: "A".to_string(),
+        },
+        ProgressJudge {
+            id: id(11),
+            name: "B".to_string(),
+        },
+    ];
+    let candidates = vec![
+        ProgressCandidate {
+            id: id(20),
+            candidate_number: 1,
+            gender: 1,
+            event_id: Some(id(2)),
+        },
+        ProgressCandidate {
+            id: id(21),
+            candidate_number: 2,
+            gender: 0,
+            event_id: Some(id(2)),
+        },
+        // Another event's candidate isn't expected to be scored in this category
+        ProgressCandidate {
+            id: id(22),
+            candidate_number: 1,
+            gender: 1,
+            event_id: Some(id(3)),
+        },
+    ];
+    let criterias = vec![
+        ProgressCriteria {
+            id: id(30),
+            name: "Poise".to_string(),
+        },
+        ProgressCriteria {
+            id: id(31),
+            name: "Beauty".to_string(),
+        },
+    ];
+
+    // Judge A scored everything, judge B skipped candidate 2 on Beauty
+    let mut scored = HashSet::new();
+    for candidate in [id(20), id(21)] {
+        for criteria in [id(30), id(31)] {
+            scored.insert((id(10), candidate, criteria));
+            scored.insert((id(11), candidate, criteria));
+        }
+    }
+    scored.remove(&(id(11), id(21), id(31)));
+
+    let progress = compute_progress(
+        &category,
+        judges,
+        &candidates,
+        criterias.clone(),
+        &scored,
+        &HashSet::new(),
+    );
+
+    assert_eq!(progress.expected, 8);
+    assert_eq!(progress.missing, 1);
+    assert_eq!(progress.judges[0].candidates.len(), 2);
+    assert!(progress
+        .incomplete_ballots()
+        .iter()
+        .all(|ballot| ballot.candidate_id != id(22)));
+    assert!(progress.judges[0].complete);
+    assert!(!progress.judges[1].complete);
+    assert_eq!(progress.judges[1].candidates[1].scored, vec![true, false]);
+
+    // Judge B is affiliated with candidate 2's college, so nothing is missing
+    let judges = vec![
+        ProgressJudge {
+            id: id(10),
+            name: "A".to_string(),
+        },
+        ProgressJudge {
+            id: id(11),
+            name: "B".to_string(),
+        },
+    ];
+    scored.remove(&(id(11), id(21), id(30)));
+
+    let progress = compute_progress(
+        &category,
+        judges,
+        &candidates,
+        criterias,
+        &scored,
+        &HashSet::from([(id(11), id(21))]),
+    );
+
+    assert_eq!(progress.expected, 6);
+    assert_eq!(progress.missing, 0);
+    assert!(progress.judges[1].candidates[1].conflict_of_interest);
+}
+
+// Shared by the tabulation tests: one event (2) with a single category (1) and criteria (30)
+// scored out of 100. Candidates are (id, candidate number), scores are (judge, candidate, score).
+#[cfg(test)]
+fn tabulation_input(
+    candidates: &[(u128, i32)],
+    judges: &[u128],
+    scores: &[(u128, u128, i64)],
+) -> super::tabulation::TabulationInput {
+    use std::collections::{HashMap, HashSet};
+
+    use rust_decimal::Decimal;
+
+    use super::category::Category;
+    use super::tabulation::{ScoreRow, TabulationCandidate, TabulationInput, TabulationJudge};
+
+    let id = uuid::Uuid::from_u128;
+
+    TabulationInput {
+        candidates: candidates
+            .iter()
+            .map(|(candidate, candidate_number)| TabulationCandidate {
+                id: id(*candidate),
+                candidate_number: *candidate_number,
+                first_name: String::new(),
+                middle_name: String::new(),
+                last_name: String::new(),
+                gender: 0,
+                event_id: Some(id(2)),
+            })
+            .collect(),
+        categories: vec![Category {
+            id: id(1),
+            name: "Category 1".to_string(),
+            weight: Decimal::ONE,
+            event_id: id(2),
+        }],
+        criterias: Vec::new(),
+        judges: judges
+            .iter()
+            .map(|judge| TabulationJudge {
+                id: id(*judge),
+                event_id: id(2),
+                score_exclusion: false,
+            })
+            .collect(),
+        scores: scores
+            .iter()
+            .map(|(judge, candidate, score)| ScoreRow {
+                candidate_id: id(*candidate),
+                category_id: id(1),
+                criteria_id: id(30),
+                judge_id: id(*judge),
+                score: Decimal::from(*score),
+                max: Decimal::ONE_HUNDRED,
+            })
+            .collect(),
+        conflicts: HashSet::new(),
+        deductions: HashMap::new(),
+        assignments: HashMap::new(),
+        judge_weights: HashMap::new(),
+    }
+}
+
+#[test]
+pub fn judge_exclusion_test() {
+    use rust_decimal::Decimal;
+
+    use super::assignment::on_panel;
+    use super::tabulation::tabulate;
+
+    let id = uuid::Uuid::from_u128;
+
+    // Judge 12 gave candidate 20 a 10 and got excluded for it
+    let mut input = tabulation_input(
+        &[(20, 1)],
+        &[10, 11, 12],
+        &[(10, 20, 80), (11, 20, 90), (12, 20, 10)],
+    );
+    input.judges[2].score_exclusion = true;
+
+    assert_eq!(tabulate(&input)[0].final_score, Decimal::from(85));
+
+    // The spreadsheet and progress get their judge columns from the same panel
+    let panel: Vec<uuid::Uuid> = input
+        .judges
+        .iter()
+        .filter(|judge| on_panel(&input.assignments, judge, &input.categories[0]))
+        .map(|judge| judge.id)
+        .collect();
+    assert_eq!(panel, vec![id(10), id(11)]);
+
+    // Reinstated, their score counts again
+    input.judges[2].score_exclusion = false;
+
+    assert_eq!(tabulate(&input)[0].final_score, Decimal::from(60));
+}
+
+#[test]
+pub fn conflict_of_interest_compensation_test() {
+    use std::collections::HashSet;
+
+    use rust_decimal::Decimal;
+
+    use super::tabulation::tabulate;
+
+    let id = uuid::Uuid::from_u128;
+
+    // Judge 12 is affiliated with the candidate's college and their 10 must not count
+    let mut input = tabulation_input(
+        &[(20, 1)],
+        &[10, 11, 12],
+        &[(10, 20, 80), (11, 20, 90), (12, 20, 10)],
+    );
+    input.conflicts = HashSet::from([(id(12), id(20))]);
+
+    let results = tabulate(&input);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].final_score, Decimal::from(85));
+}
+
+#[test]
+pub fn rank_correlation_test() {
+    use super::analytics::{kendall_tau_b, ranks, spearman, std_dev};
+
+    assert_eq!(ranks(&[70.0, 90.0, 80.0, 90.0]), vec![4.0, 1.5, 3.0, 1.5]);
+    assert!((std_dev(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]) - 2.0).abs() < 1e-9);
+
+    let panel = [95.0, 90.0, 85.0, 80.0, 75.0];
+
+    // Same order as the panel, even if harsher
+    let harsh = [60.0, 55.0, 50.0, 45.0, 40.0];
+    assert!((spearman(&harsh, &panel).unwrap() - 1.0).abs() < 1e-9);
+    assert!((kendall_tau_b(&harsh, &panel).unwrap() - 1.0).abs() < 1e-9);
+
+    // Exact opposite of the panel
+    let reversed = [75.0, 80.0, 85.0, 90.0, 95.0];
+    assert!((spearman(&reversed, &panel).unwrap() + 1.0).abs() < 1e-9);
+    assert!((kendall_tau_b(&reversed, &panel).unwrap() + 1.0).abs() < 1e-9);
+
+    // A judge that gave everyone the same score can't be correlated
+    assert!(spearman(&[80.0; 5], &panel).is_none());
+}
+
+#[test]
+pub fn judge_analytics_test() {
+    use std::collections::HashSet;
+
+    use std::collections::HashMap;
+
+    use super::analytics::{
+        category_panel, final_scores_without, judge_totals, panel_means_without,
+    };
+
+    let id = uuid::Uuid::from_u128;
+
+    // Judge 12 is much harsher than the rest, judge 11 is affiliated with candidate 21
+    let mut input = tabulation_input(
+        &[(20, 1), (21, 2)],
+        &[10, 11, 12],
+        &[
+            (10, 20, 90),
+            (11, 20, 80),
+            (12, 20, 40),
+            (10, 21, 70),
+            (11, 21, 100),
+            (12, 21, 30),
+        ],
+    );
+    input.conflicts = HashSet::from([(id(11), id(21))]);
+
+    let totals = judge_totals(&input);
+    assert_eq!(totals.len(), 5);
+
+    let totals: Vec<_> = totals.iter().collect();
+    let panel = category_panel(&input, &input.categories[0]);
+    assert_eq!(panel, HashSet::from([id(10), id(11), id(12)]));
+
+    // Compared against the rest of the panel only, not a mean that includes their own 40
+    let means = panel_means_without(&totals, id(12), &panel);
+    assert_eq!(means[&id(20)], 85.0);
+    assert_eq!(means[&id(21)], 70.0);
+
+    // The final ranking the judge is correlated with doesn't count them either
+    let final_scores = final_scores_without(&input, id(12));
+    assert_eq!(final_scores[&id(20)], 85.0);
+    assert_eq!(final_scores[&id(21)], 70.0);
+
+    // Judge 11 isn't assigned to the category, so judge 12 is only compared against judge 10
+    let category_id = input.categories[0].id;
+    input.assignments = HashMap::from([(category_id, HashSet::from([id(10), id(12)]))]);
+
+    let panel = category_panel(&input, &input.categories[0]);
+    assert_eq!(panel, HashSet::from([id(10), id(12)]));
+
+    let means = panel_means_without(&totals, id(12), &panel);
+    assert_eq!(means[&id(20)], 90.0);
+    assert_eq!(means[&id(21)], 70.0);
+}
+
+#[test]
+pub fn simulation_test() {
+    use super::simulation::{simulate, Overrides};
+    use super::tabulation::RoundingPolicy;
+
+    let id = uuid::Uuid::from_u128;
+
+    // Judge 12 alone puts candidate 21 ahead
+    let input = tabulation_input(
+        &[(20, 1), (21, 2)],
+        &[10, 11, 12],
+        &[
+            (10, 20, 80),
+            (11, 20, 80),
+            (12, 20, 50),
+            (10, 21, 75),
+            (11, 21, 75),
+            (12, 21, 100),
+        ],
+    );
+
+    let overrides = Overrides {
+        excluded_judges: vec![id(12)],
+        ..Default::default()
+    };
+
+    let simulation = simulate(&input, &overrides, &RoundingPolicy::default());
+    let first = &simulation.results[0];
+
+    assert_eq!(first.candidate_id, id(20));
+    assert_eq!(first.official_rank, 2);
+    assert_eq!(first.simulated_rank, 1);
+    assert_eq!(first.rank_delta, 1);
+}
+
+#[test]
+pub fn rank_by_gender_test() {
+    use super::tabulation::tabulate;
+
+    let mut input = tabulation_input(
+        &[(20, 1), (21, 2), (22, 3)],
+        &[10],
+        &[(10, 20, 90), (10, 21, 80), (10, 22, 70)],
+    );
+
+    // Every gender in the input is ranked on its own, not just 0 and 1
+    input.candidates[1].gender = 1;
+    input.candidates[2].gender = 2;
+
+    assert!(tabulate(&input).iter().all(|result| result.rank == 1));
+}
+
+#[test]
+pub fn final_scores_test() {
+    use rust_decimal::Decimal;
+
+    use super::results::ranking;
+    use super::score::final_scores;
+    use super::tabulation::{Method, RoundingPolicy};
+
+    let input = tabulation_input(
+        &[(20, 1), (21, 2)],
+        &[10, 11, 12],
+        &[
+            (10, 20, 90),
+            (11, 20, 85),
+            (12, 20, 85),
+            (10, 21, 70),
+            (11, 21, 75),
+            (12, 21, 80),
+        ],
+    );
+    let rounding = RoundingPolicy::default();
+
+    // Reading the final scores only computes them, doing it twice gives the same thing
+    let first = serde_json::to_value(final_scores(&input, &rounding)).unwrap();
+    let second = serde_json::to_value(final_scores(&input, &rounding)).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(
+        first[0]["final_score"],
+        serde_json::to_value(Decimal::new(86667, 3)).unwrap()
+    );
+
+    // Publishing stores exactly what was shown
+    let published = serde_json::to_value(ranking(&input, Method::default(), &rounding)).unwrap();
+    for (shown, stored) in first
+        .as_array()
+        .unwrap()
+        .iter()
+        .zip(published.as_array().unwrap())
+    {
+        assert_eq!(shown["candidate_id"], stored["candidate_id"]);
+        assert_eq!(shown["final_score"], stored["final_score"]);
+    }
+
+    // A half scored candidate of another event is left out of this event's results
+    let mut both_events = input.clone();
+    let mut other = both_events.candidates[0].clone();
+    other.id = uuid::Uuid::from_u128(22);
+    other.event_id = Some(uuid::Uuid::from_u128(3));
+    both_events.candidates.push(other);
+
+    assert_eq!(final_scores(&both_events, &rounding).len(), 3);
+
+    let event_scores = serde_json::to_value(final_scores(
+        &both_events.for_event(uuid::Uuid::from_u128(2)),
+        &rounding,
+    ))
+    .unwrap();
+    assert_eq!(event_scores, first);
+}
+
+#[test]
+pub fn snapshot_test() {
+    use rust_decimal::Decimal;
+
+    use super::results::{content_hash, diff, snapshot_content, Snapshot};
+    use super::tabulation::RoundingPolicy;
+
+    let id = uuid::Uuid::from_u128;
+    let scores = [(10, 20, 90), (11, 20, 80), (10, 21, 70), (11, 21, 80)];
+
+    let published = snapshot_content(
+        &tabulation_input(&[(20, 1), (21, 2)], &[10, 11], &scores),
+        Vec::new(),
+        Vec::new(),
+        RoundingPolicy::default(),
+        None,
+    );
+    let canonical = published.canonical();
+    let hash = content_hash(&canonical);
+    assert_eq!(hash, published.hash());
+
+    let snapshot = Snapshot::new(id(1), chrono::Utc::now(), &canonical, hash.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&snapshot).unwrap()["hash_valid"], true);
+
+    // Verifying only looks at the stored bytes, a field today's types don't know about is fine
+    let mut extended: serde_json::Value = serde_json::from_str(&canonical).unwrap();
+    extended["retired_field"] = serde_json::json!(true);
+    let extended = extended.to_string();
+    let snapshot_extended = Snapshot::new(
+        id(1),
+        chrono::Utc::now(),
+        &extended,
+        content_hash(&extended),
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::to_value(&snapshot_extended).unwrap()["hash_valid"],
+        true
+    );
+
+    // Any change to the stored bytes is caught
+    let tampered = canonical.replace("\"rank\":1", "\"rank\":2");
+    assert_ne!(tampered, canonical);
+    let snapshot_tampered = Snapshot::new(id(1), chrono::Utc::now(), &tampered, hash).unwrap();
+    assert_eq!(
+        serde_json::to_value(&snapshot_tampered).unwrap()["hash_valid"],
+        false
+    );
+
+    // Nothing changed since publishing
+    let unchanged = serde_json::to_value(diff(&snapshot, &published).unwrap()).unwrap();
+    assert_eq!(unchanged["changed"], false);
+    assert_eq!(unchanged["candidates"][0]["rank_delta"], 0);
+
+    // Candidate 21 overtakes candidate 20
+    let live = snapshot_content(
+        &tabulation_input(
+            &[(20, 1), (21, 2)],
+            &[10, 11],
+            &[(10, 20, 90), (11, 20, 80), (10, 21, 95), (11, 21, 95)],
+        ),
+        Vec::new(),
+        Vec::new(),
+        RoundingPolicy::default(),
+        None,
+    );
+    let changed = serde_json::to_value(diff(&snapshot, &live).unwrap()).unwrap();
+    assert_eq!(changed["changed"], true);
+
+    let candidate = |candidate_id: u128| {
+        changed["candidates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|candidate| candidate["candidate_id"] == id(candidate_id).to_string())
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(candidate(20)["rank_delta"], -1);
+    assert_eq!(candidate(21)["rank_delta"], 1);
+    assert_eq!(
+        candidate(21)["score_delta"],
+        serde_json::to_value(Decimal::from(20)).unwrap()
+    );
+}
+
+#[test]
+pub fn score_chain_test() {
+    use rust_decimal::Decimal;
+
+    use super::chain::{verify, ChainEntry, GENESIS_HASH};
+
+    let id = uuid::Uuid::from_u128;
+
+    let mut entries: Vec<ChainEntry> = Vec::new();
+
+    for (i, score) in [8, 9, 7].into_iter().enumerate() {
+        let mut entry = ChainEntry {
+            id: i as i64 + 1,
+            event_id: id(1),
+            score_id: id(10 + i as u128),
+            action: "insert".to_string(),
+            score: Decimal::from(score),
+            max: Decimal::from(10),
+            candidate_id: id(20),
+            criteria_id: id(30),
+            category_id: id(40),
+            judge_id: id(50),
+            recorded_at: chrono::DateTime::from_timestamp(1_700_000_000 + i as i64, 0).unwrap(),
+            prev_hash: entries
+                .last()
+                .map(|entry| entry.hash.clone())
+                .unwrap_or_else(|| GENESIS_HASH.to_string()),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        entries.push(entry);
+    }
+
+    let verification = verify(&entries);
+
+    assert!(verification.valid);
+    assert_eq!(verification.length, 3);
+    assert_eq!(verification.head_hash, entries[2].hash);
+
+    // Quietly changing a score breaks the chain at that entry
+    entries[1].score = Decimal::from(10);
+
+    let verification = verify(&entries);
+
+    assert!(!verification.valid);
+    assert!(verification.broken_at.is_some());
+}
+
+#[test]
+pub fn decimal_score_test() {
+    use rust_decimal::Decimal;
+
+    use super::score::check_score;
+
+    let (max_score, score_step) = (Decimal::from(10), Decimal::new(5, 1));
+
+    assert!(check_score(Decimal::new(85, 1), max_score, score_step).is_ok());
+    assert!(check_score(Decimal::from(10), max_score, score_step).is_ok());
+    assert!(check_score(Decimal::new(825, 2), max_score, score_step).is_err());
+    assert!(check_score(Decimal::new(105, 1), max_score, score_step).is_err());
+    assert!(check_score(Decimal::from(-1), max_score, score_step).is_err());
+}
+
+#[test]
+pub fn rounding_policy_test() {
+    use rust_decimal::Decimal;
+
+    use super::tabulation::{RoundingMode, RoundingPolicy};
+
+    let value = Decimal::new(853335, 4);
+    let policy = |mode: RoundingMode| RoundingPolicy { precision: 3, mode };
+
+    assert_eq!(policy(RoundingMode::HalfUp).format(value), "85.334");
+    assert_eq!(policy(RoundingMode::HalfEven).format(value), "85.334");
+    assert_eq!(
+        policy(RoundingMode::HalfEven).format(Decimal::new(853345, 4)),
+        "85.334"
+    );
+    assert_eq!(
+        policy(RoundingMode::Truncate).format(Decimal::new(853339, 4)),
+        "85.333"
+    );
+    assert_eq!(
+        RoundingPolicy::default().format(Decimal::new(853, 1)),
+        "85.300"
+    );
+}
+
+#[test]
+pub fn event_readiness_test() {
+    use std::collections::{HashMap, HashSet};
+
+    use rust_decimal::Decimal;
+
+    use super::category::{check_weight, Category};
+    use super::readiness::{check_readiness, ReadinessCriteria, ReadinessJudge};
+
+    let id = uuid::Uuid::from_u128;
+
+    assert!(check_weight(Decimal::new(3, 1), Decimal::new(7, 1)).is_ok());
+    assert!(check_weight(Decimal::new(4, 1), Decimal::new(7, 1)).is_err());
+    assert!(check_weight(Decimal::ZERO, Decimal::ZERO).is_err());
+
+    let category = |category: u128, weight: Decimal| Category {
+        id: id(category),
+        name: format!("Category {category}"),
+        weight,
+        event_id: id(1),
+    };
+    let categories = vec![
+        category(10, Decimal::new(6, 1)),
+        category(11, Decimal::new(3, 1)),
+    ];
+    let criterias = vec![ReadinessCriteria {
+        id: id(20),
+        name: "Poise".to_string(),
+        max_score: Some(Decimal::ZERO),
+        weight: None,
+        category_id: id(10),
+    }];
+    let judge = |judge: u128| ReadinessJudge {
+        id: id(judge),
+        name: format!("Judge {judge}"),
+    };
+    let judges = vec![judge(30), judge(31)];
+
+    let readiness = check_readiness(&categories, &criterias, &judges, &HashMap::new());
+
+    assert!(!readiness.ready);
+    assert_eq!(readiness.weight_sum, Decimal::new(9, 1));
+    assert_eq!(readiness.categories_without_criteria.len(), 1);
+    assert_eq!(readiness.criterias_without_max_score.len(), 1);
+    assert!(readiness.judges_without_assignment.is_empty());
+    assert_eq!(readiness.issues.len(), 3);
+
+    // Both categories have their own panel and judge 31 is on neither
+    let assignments = HashMap::from([
+        (id(10), HashSet::from([id(30)])),
+        (id(11), HashSet::from([id(30)])),
+    ]);
+    let readiness = check_readiness(&categories, &criterias, &judges, &assignments);
+
+    assert_eq!(readiness.judges_without_assignment.len(), 1);
+    assert_eq!(readiness.judges_without_assignment[0].id, id(31));
+}
+
+#[test]
+pub fn criteria_weights_test() {
+    use std::collections::HashMap;
+
+    use rust_decimal::Decimal;
+
+    use super::tabulation::{category_total, criteria_weights, TabulationCriteria};
+
+    let id = uuid::Uuid::from_u128;
+    let criteria = |criteria: u128, category: u128, weight: Option<Decimal>| TabulationCriteria {
+        id: id(criteria),
+        category_id: id(category),
+        weight,
+    };
+
+    // Category 1 is fully weighted 30/30/40, category 2 only partly so it falls back to max scores
+    let weights = criteria_weights(&[
+        criteria(10, 1, Some(Decimal::new(3, 1))),
+        criteria(11, 1, Some(Decimal::new(3, 1))),
+        criteria(12, 1, Some(Decimal::new(4, 1))),
+        criteria(20, 2, Some(Decimal::new(5, 1))),
+        criteria(21, 2, None),
+    ]);
+
+    assert_eq!(weights.len(), 3);
+
+    let scores = |criterias: [u128; 3]| {
+        criterias
+            .into_iter()
+            .zip([10, 5, 10])
+            .map(|(criteria, score)| (id(criteria), Decimal::from(score), Decimal::from(10)))
+            .collect::<Vec<_>>()
+    };
+
+    // 0.3 * 100% + 0.3 * 50% + 0.4 * 100% = 85% of 30
+    assert_eq!(
+        category_total(scores([10, 11, 12]), &weights),
+        (Decimal::new(255, 1), Decimal::from(30))
+    );
+
+    // No weights, the plain sum
+    assert_eq!(
+        category_total(scores([20, 21, 22]), &HashMap::new()),
+        (Decimal::from(25), Decimal::from(30))
+    );
+}
+
+#[test]
+pub fn deduction_test() {
+    use std::collections::HashMap;
+
+    use rust_decimal::Decimal;
+
+    use super::tabulation::{apply_deduction, category_breakdown, tabulate};
+
+    let id = uuid::Uuid::from_u128;
+
+    // Candidate 20 went overtime and loses 10 of the category's 100 points
+    let mut input = tabulation_input(
+        &[(20, 1), (21, 2)],
+        &[10, 11],
+        &[(10, 20, 90), (11, 20, 90), (10, 21, 85), (11, 21, 85)],
+    );
+    input.deductions = HashMap::from([((id(20), id(1)), Decimal::TEN)]);
+
+    let results = tabulate(&input);
+
+    assert_eq!(results[0].final_score, Decimal::from(80));
+    assert_eq!(results[0].rank, 2);
+
+    let breakdown = category_breakdown(&input);
+
+    assert_eq!(breakdown[0].score, Decimal::from(160));
+    assert_eq!(breakdown[0].deduction, Decimal::TEN);
+
+    // Never below zero
+    assert_eq!(
+        apply_deduction(Decimal::from(5), Decimal::from(10), Decimal::ONE_HUNDRED),
+        Decimal::ZERO
+    );
+}
+
+#[test]
+pub fn event_status_test() {
+    use super::event::EventStatus::*;
+
+    assert!(Draft.can_transition_to(Setup));
+    assert!(Setup.can_transition_to(Live));
+    assert!(Closed.can_transition_to(Live));
+
+    // Scoring can't start before the event is set up and archived events are final
+    assert!(!Draft.can_transition_to(Live));
+    assert!(!Archived.can_transition_to(Closed));
+    assert!(!Live.can_transition_to(Setup));
+
+    assert!(Setup.allows_setup());
+    assert!(!Live.allows_setup());
+    assert!(Live.allows_scoring());
+    assert!(!Closed.allows_scoring());
+
+    // Deductions, exclusions and locks need a closed event to be reopened first
+    assert!(Live.allows_changes());
+    assert!(!Closed.allows_changes());
+    assert!(!Archived.allows_changes());
+}
+
+#[test]
+pub fn judge_weights_test() {
+    use std::collections::HashMap;
+
+    use rust_decimal::Decimal;
+
+    use super::tabulation::{panel_factors, tabulate};
+
+    let id = uuid::Uuid::from_u128;
+
+    // Equal weights change nothing
+    assert_eq!(panel_factors(&[Decimal::TWO; 3]), vec![Decimal::ONE; 3]);
+
+    // Judge 10 is the head judge and counts three times as much as judge 11
+    let mut input = tabulation_input(
+        &[(20, 1), (21, 2)],
+        &[10, 11],
+        &[(10, 20, 90), (11, 20, 60), (10, 21, 70), (11, 21, 85)],
+    );
+    input.judge_weights = HashMap::from([((id(10), id(1)), Decimal::from(3))]);
+
+    let results = tabulate(&input);
+
+    assert_eq!(results[0].final_score, Decimal::new(825, 1));
+    assert_eq!(results[0].rank, 1);
+    assert_eq!(results[1].final_score, Decimal::new(7375, 2));
+}
+
+#[test]
+pub fn score_correction_routing_test() {
+    use crate::realtime::subscription::{Role, Subscription, Topic};
+
+    let event_id = uuid::Uuid::from_u128(1);
+    let judge_id = uuid::Uuid::from_u128(10);
+    let other_judge_id = uuid::Uuid::from_u128(11);
+
+    // Pending requests are for the tabulator, the outcome goes back to the judge as well
+    let pending = Topic::from_payload(&format!(
+        r#"{{"type": "score_correction", "event_id": "{event_id}", "judge_id": "{judge_id}"}}"#
+    ));
+    let reviewed = Topic::from_payload(&format!(
+        r#"{{"type": "score_correction_reviewed", "event_id": "{event_id}", "judge_id": "{judge_id}"}}"#
+    ));
+
+    let judge = Subscription {
+        role: Role::Judge,
+        event_id: Some(event_id),
+        judge_id: Some(judge_id),
+        ..Default::default()
+    };
+    assert!(!judge.matches(&pending));
+    assert!(judge.matches(&reviewed));
+
+    // Other judges of the event don't get to see how someone else's request went
+    let other_judge = Subscription {
+        judge_id: Some(other_judge_id),
+        ..judge.clone()
+    };
+    assert!(!other_judge.matches(&pending));
+    assert!(!other_judge.matches(&reviewed));
+
+    let unidentified_judge = Subscription {
+        judge_id: None,
+        ..judge.clone()
+    };
+    assert!(!unidentified_judge.matches(&reviewed));
+
+    let tabulator = Subscription {
+        role: Role::Tabulator,
+        event_id: Some(event_id),
+        ..Default::default()
+    };
+    assert!(tabulator.matches(&pending));
+    assert!(tabulator.matches(&reviewed));
+
+    // The progress matrix shows every judge's ballots, that's for the tabulator only
+    let progress = Topic::from_payload(&format!(
+        r#"{{"type": "progress", "event_id": "{event_id}"}}"#
+    ));
+    assert!(!judge.matches(&progress));
+    assert!(tabulator.matches(&progress));
+}
+
+#[test]
+pub fn score_version_test() {
+    use super::score::{check_version, Score, UpdateScore};
+
+    let score_id = uuid::Uuid::from_u128(1);
+
+    // Updates without the version they edited would go back to last write wins
+    assert!(serde_json::from_str::<UpdateScore>(&format!(
+        r#"{{"score_id": "{score_id}", "score": 8.5}}"#
+    ))
+    .is_err());
+    assert!(serde_json::from_str::<UpdateScore>(&format!(
+        r#"{{"score_id": "{score_id}", "score": 8.5, "version": 2}}"#
+    ))
+    .is_ok());
+
+    let current: Score = serde_json::from_value(serde_json::json!({
+        "id": score_id,
+        "score": 9,
+        "max": 10,
+        "time_of_scoring": "2026-10-18T09:00:00Z",
+        "version": 3,
+        "candidate_id": uuid::Uuid::from_u128(2),
+        "criteria_id": uuid::Uuid::from_u128(3),
+        "category_id": uuid::Uuid::from_u128(4),
+        "judge_id": uuid::Uuid::from_u128(5),
+    }))
+    .unwrap();
+
+    assert!(check_version(3, &current).is_ok());
+
+    // A stale update is rejected and gets the score as it is now
+    let stale = check_version(2, &current).unwrap_err();
+    assert_eq!(stale.status(), axum::http::StatusCode::CONFLICT);
+    assert_eq!(stale.to_json()["code"], "conflict");
+    assert_eq!(stale.to_json()["details"]["current"]["version"], 3);
+    assert_eq!(stale.to_json()["details"]["current"]["score"], 9.0);
+}
+
+#[test]
+pub fn error_response_test() {
+    use axum::http;
+    use serde_json::json;
+
+    use crate::error::AppError;
+
+    let not_found = AppError::from(sqlx::Error::RowNotFound);
+    assert_eq!(not_found.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(not_found.to_json()["code"], "not_found");
+
+    let conflict = AppError::conflict("Score was changed by someone else")
+        .with_details(json!({ "current": { "version": 3 } }));
+    assert_eq!(conflict.status(), http::StatusCode::CONFLICT);
+    assert_eq!(conflict.to_json()["details"]["current"]["version"], 3);
+
+    assert_eq!(
+        AppError::validation("Weight must be greater than 0").status(),
+        http::StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    // Nothing about the database makes it to the client
+    let internal = AppError::from(sqlx::Error::PoolTimedOut);
+    assert_eq!(internal.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        internal.to_json(),
+        json!({
+            "code": "internal_error",
+            "message": "Something went wrong on the server",
+            "details": null,
+        })
+    );
+
+    // Requests axum's extractors reject get the same kind of body
+    #[derive(serde::Deserialize)]
+    struct Payload {
+        #[allow(dead_code)]
+        score: i32,
+    }
+
+    let app = axum::Router::new()
+        .route(
+            "/scores/:score_id",
+            axum::routing::post(
+                |_: axum::extract::Path<uuid::Uuid>, _: axum::Json<Payload>| async {},
+            ),
+        )
+        .route(
+            "/scores",
+            axum::routing::get(|_: axum::extract::Query<Payload>| async {}),
+        )
+        .layer(axum::middleware::map_response(crate::error::map_rejection));
+
+    let send = |request: http::Request<axum::body::Body>| {
+        use axum::extract::FromRequest;
+        use tower::ServiceExt;
+
+        let app = app.clone();
+
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body =
+                    axum::body::Bytes::from_request(http::Request::new(response.into_body()), &())
+                        .await
+                        .unwrap();
+
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                )
+            })
+    };
+    let post = |uri: &str, body: &str| {
+        http::Request::post(uri)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap()
+    };
+    let score_uri = format!("/scores/{}", uuid::Uuid::from_u128(1));
+
+    let (status, body) = send(post(&score_uri, "{"));
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "bad_request");
+
+    let (status, body) = send(post(&score_uri, r#"{"score": "high"}"#));
+    assert_eq!(status, http::StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+    assert!(body["message"].as_str().unwrap().contains("score"));
+
+    let (status, body) = send(post("/scores/not-a-uuid", r#"{"score": 1}"#));
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "bad_request");
 
-}
\ No newline at end of file
+    let (status, body) = send(
+        http::Request::get("/scores?score=high")
+            .body(axum::body::Body::empty())
+            .unwrap(),
+    );
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "bad_request");
+}
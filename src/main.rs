@@ -4,32 +4,51 @@
 
 use anyhow::Context;
 use axum::{
-    extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
-    },
+    extract::{FromRef, State},
     http,
-    response::Response,
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use dotenv::dotenv;
-use futures::{sink::SinkExt, stream::StreamExt};
-use sqlx::postgres::PgListener;
+use serde_json::json;
+use sqlx::PgPool;
 use std::env;
-use tokio::{net::TcpListener, sync::broadcast};
+use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 
 mod error;
 mod handlers;
+mod realtime;
 
-use handlers::{auth, candidate, category, college, criteria, event, judge, note, score};
+use handlers::{
+    affiliation, analytics, assignment, auth, candidate, category, chain, college, correction,
+    criteria, deduction, event, judge, note, progress, readiness, results, score, simulation,
+};
+use realtime::Realtime;
+
+#[derive(Clone)]
+pub struct AppState {
+    pool: PgPool,
+    realtime: Realtime,
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Realtime {
+    fn from_ref(state: &AppState) -> Self {
+        state.realtime.clone()
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<(), anyhow::Error> {
     dotenv().ok();
 
-    let (tx, _rx): (broadcast::Sender<String>, _) = broadcast::channel(50);
+    let realtime = Realtime::new(50);
 
     let db_url = env::var("DATABASE_URL").context("DATABASE_URL env not found.")?;
     let ip_addr = env::var("IP_ADDRESS").unwrap_or("127.0.0.1".to_string());
@@ -39,33 +58,38 @@ async fn main() -> anyhow::Result<(), anyhow::Error> {
         .connect(&db_url)
         .await?;
 
-    let mut pg_listener = PgListener::connect_with(&pool).await?;
-
-    pg_listener.listen_all(vec!["updates"]).await?;
-
-    println!("\nNow listening to Postgres...\n");
-
-    db_ws_listen(pg_listener, tx.clone());
+    realtime::listener::spawn(pool.clone(), realtime.clone());
+    realtime::presence::spawn_sweeper(pool.clone(), realtime.clone());
 
     let app = Router::new()
         // WebSocket
-        .route("/ws", get(ws_handler))
-        .with_state(tx)
+        .route("/ws", get(realtime::ws::ws_handler))
+        // Server-Sent Events for read-only displays
+        .route("/sse", get(realtime::sse::sse_handler))
         .route("/", get(health))
+        .route("/presence", get(realtime::presence::get_presence))
         // Auth
         .route("/login", post(auth::login))
         .route("/logout", post(auth::logout))
         // Events
         .route("/events", post(event::create_event).get(event::get_events))
         .route("/events/:event_id", get(event::get_event))
+        .route("/events/:event_id/status", post(event::update_event_status))
+        .route("/events/:event_id/readiness", get(readiness::get_readiness))
         // Categories
         .route(
             "/events/:event_id/categories",
-            post(category::create_category).get(category::get_categories).put(category::update_category),
+            post(category::create_category)
+                .get(category::get_categories)
+                .put(category::update_category),
         )
         .route(
             "/events/:event_id/categories/:category_id",
-            get(category::get_category),
+            get(category::get_category).patch(category::edit_category),
+        )
+        .route(
+            "/events/:event_id/categories/:category_id/lock",
+            post(category::lock_category),
         )
         // Criterias
         .route(
@@ -85,17 +109,80 @@ async fn main() -> anyhow::Result<(), anyhow::Error> {
         .route("/candidates/:candidate_id", get(candidate::get_candidate))
         .route("/judges", post(judge::create_judge).get(judge::get_judges))
         .route("/judges/:judge_id", get(judge::get_judge))
+        .route("/judges/:judge_id/weight", post(judge::update_weight))
+        .route(
+            "/judges/:judge_id/exclusion",
+            post(judge::update_exclusion).get(judge::get_exclusion_logs),
+        )
+        .route(
+            "/judges/:judge_id/affiliations",
+            post(affiliation::create_affiliation).get(affiliation::get_affiliations),
+        )
+        .route(
+            "/judges/:judge_id/affiliations/logs",
+            get(affiliation::get_affiliation_logs),
+        )
+        .route(
+            "/judges/:judge_id/affiliations/:college_id",
+            delete(affiliation::delete_affiliation),
+        )
+        .route(
+            "/judges/:judge_id/assignments",
+            post(assignment::create_assignment).get(assignment::get_assignments),
+        )
+        .route(
+            "/judges/:judge_id/assignments/:category_id",
+            delete(assignment::delete_assignment),
+        )
         .route(
             "/scores",
             post(score::submit_score).get(score::get_candidate_scores),
         )
         .route("/scores/update", post(score::update_score))
+        .route(
+            "/scores/corrections",
+            post(correction::create_correction).get(correction::get_corrections),
+        )
+        .route(
+            "/scores/corrections/:correction_id/review",
+            post(correction::review_correction),
+        )
+        // Deductions
+        .route(
+            "/deductions",
+            post(deduction::create_deduction).get(deduction::get_deductions),
+        )
+        .route("/deductions/logs", get(deduction::get_deduction_logs))
+        .route(
+            "/deductions/:deduction_id",
+            delete(deduction::delete_deduction),
+        )
+        .route("/scores/progress", get(progress::get_progress))
+        .route("/scores/validation", get(progress::get_validation))
+        .route("/analytics/judges", get(analytics::get_judge_analytics))
         .route("/scores/final", get(score::get_candidate_final_scores))
+        .route("/scores/simulate", post(simulation::simulate_results))
+        .route("/results/publish", post(results::publish_results))
+        .route("/events/:event_id/chain/verify", get(chain::verify_chain))
+        .route("/results/snapshots", get(results::get_snapshots))
+        .route(
+            "/results/snapshots/:snapshot_id",
+            get(results::get_snapshot),
+        )
+        .route(
+            "/results/snapshots/:snapshot_id/download",
+            get(results::download_snapshot),
+        )
+        .route(
+            "/results/snapshots/:snapshot_id/diff",
+            get(results::diff_snapshot),
+        )
         .route("/scores/download", get(score::generate_score_spreadsheet))
         .route("/notes", post(note::create_note).get(note::get_note))
         .route("/college", get(college::get_colleges))
+        .layer(axum::middleware::map_response(error::map_rejection))
         .layer(CorsLayer::permissive())
-        .with_state(pool);
+        .with_state(AppState { pool, realtime });
 
     // For local development (NOT EXPOSURE TO THE NETWORK) it must be [127.0.0.1]
     let listener = TcpListener::bind(format!("{}:8000", ip_addr)).await?;
@@ -110,66 +197,21 @@ async fn main() -> anyhow::Result<(), anyhow::Error> {
     Ok(())
 }
 
-async fn health() -> (http::StatusCode, String) {
-    (http::StatusCode::OK, "Hello, World!".to_string())
-}
-
-// Listen to the database in real-time and send the notification to the websocket
-fn db_ws_listen(mut pg_listener: PgListener, tx: broadcast::Sender<String>) {
-    tokio::spawn(async move {
-        loop {
-            while let Some(notification) = pg_listener
-                .try_recv()
-                .await
-                .context("Failed to receive notification.")
-                .unwrap()
-            {
-                let payload = notification.payload();
-
-                tx.send(payload.to_string())
-                    .context("Failed to send payload.")
-                    .unwrap();
-
-                println!("Notification:\n{payload:?}\n");
-            }
-
-            println!("Connection to Postgres lost.");
-        }
-    });
-}
-
-async fn ws_handler(
-    ws: WebSocketUpgrade,
-    State(state): State<broadcast::Sender<String>>,
-) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
-}
-
-async fn handle_socket(socket: WebSocket, tx: broadcast::Sender<String>) {
-    let (mut sender, mut receiver) = socket.split();
-
-    let mut rx = tx.subscribe();
-
-    // Spawn the first task that will receive broadcast messages and send text messages over the websocket to our client.
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if sender.send(Message::Text(msg)).await.is_err() {
-                break;
-            }
-        }
-    });
-
-    // Spawn a task that takes messages from the websocket and sends them to all broadcast subscribers.
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(Message::Text(event))) = receiver.next().await {
-            println!("Sending Event:\n{event}\n");
-
-            tx.send(event).unwrap();
-        }
-    });
-
-    tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
+async fn health(
+    State(realtime): State<Realtime>,
+) -> (http::StatusCode, axum::Json<serde_json::Value>) {
+    let listener = realtime.listener.report();
+    let status = if realtime.listener.is_connected() {
+        "ok"
+    } else {
+        "degraded"
     };
+
+    (
+        http::StatusCode::OK,
+        axum::Json(json!({
+            "status": status,
+            "listener": listener,
+        })),
+    )
 }
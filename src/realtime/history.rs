@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+use serde_json::{json, Value};
+
+// Bounded buffer of the most recent server events so reconnecting clients can catch up
+#[derive(Debug)]
+pub struct History {
+    capacity: usize,
+    latest_seq: u64,
+    events: VecDeque<(u64, String)>,
+}
+
+#[derive(Debug)]
+pub struct Replay {
+    pub events: Vec<String>,
+    // The requested events are no longer (or were never) in the buffer
+    pub gap: bool,
+    pub latest_seq: u64,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            latest_seq: 0,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    // Assigns the next sequence number to the payload and keeps it around for replays
+    pub fn record(&mut self, payload: &str) -> String {
+        self.latest_seq += 1;
+
+        let message = with_seq(payload, self.latest_seq);
+
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+
+        self.events.push_back((self.latest_seq, message.clone()));
+
+        message
+    }
+
+    pub fn since(&self, last_seq: u64) -> Replay {
+        let oldest_seq = self
+            .events
+            .front()
+            .map(|(seq, _)| *seq)
+            .unwrap_or(self.latest_seq + 1);
+
+        // Either the server restarted since the client last saw something, or the
+        // buffer already dropped some of the events the client missed
+        let gap = last_seq > self.latest_seq || last_seq + 1 < oldest_seq;
+
+        let events = self
+            .events
+            .iter()
+            .filter(|(seq, _)| *seq > last_seq)
+            .map(|(_, message)| message.clone())
+            .collect();
+
+        Replay {
+            events,
+            gap,
+            latest_seq: self.latest_seq,
+        }
+    }
+}
+
+fn with_seq(payload: &str, seq: u64) -> String {
+    match serde_json::from_str::<Value>(payload) {
+        Ok(Value::Object(mut object)) => {
+            object.insert("seq".to_string(), json!(seq));
+
+            Value::Object(object).to_string()
+        }
+        Ok(value) => json!({ "seq": seq, "payload": value }).to_string(),
+        Err(_) => json!({ "seq": seq, "payload": payload }).to_string(),
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+
+use super::{resync_message, Realtime};
+
+const CHANNEL: &str = "updates";
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+pub struct ListenerStatus {
+    connected: AtomicBool,
+    reconnects: AtomicU64,
+    notifications: AtomicU64,
+    last_error: Mutex<Option<String>>,
+    last_notification_at: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListenerReport {
+    connected: bool,
+    reconnects: u64,
+    notifications: u64,
+    last_error: Option<String>,
+    last_notification_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ListenerStatus {
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) -> ListenerReport {
+        ListenerReport {
+            connected: self.is_connected(),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            notifications: self.notifications.load(Ordering::Relaxed),
+            last_error: self.last_error.lock().unwrap().clone(),
+            last_notification_at: *self.last_notification_at.lock().unwrap(),
+        }
+    }
+
+    fn set_connected(&self) {
+        self.connected.store(true, Ordering::Relaxed);
+    }
+
+    fn set_disconnected(&self, error: impl Into<String>) {
+        self.connected.store(false, Ordering::Relaxed);
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap() = Some(error.into());
+    }
+
+    fn received(&self) {
+        self.notifications.fetch_add(1, Ordering::Relaxed);
+        *self.last_notification_at.lock().unwrap() = Some(chrono::Utc::now());
+    }
+}
+
+// Listen to the database in real-time and send the notifications to the websocket.
+// The task never gives up, if Postgres goes away it keeps reconnecting with a backoff.
+pub fn spawn(pool: PgPool, realtime: Realtime) {
+    tokio::spawn(async move {
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            if let Err(err) = listen(&pool, &realtime, &mut backoff).await {
+                eprintln!("Postgres listener failed: {err:?}");
+
+                realtime.listener.set_disconnected(err.to_string());
+            }
+
+            println!("Reconnecting to Postgres in {backoff:?}...\n");
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+async fn listen(
+    pool: &PgPool,
+    realtime: &Realtime,
+    backoff: &mut Duration,
+) -> Result<(), sqlx::Error> {
+    let mut pg_listener = PgListener::connect_with(pool).await?;
+
+    pg_listener.listen(CHANNEL).await?;
+
+    // Anything sent while we were away is gone, so let the clients know
+    if realtime.listener.reconnects.load(Ordering::Relaxed) > 0 {
+        let _ = realtime.tx.send(resync_message("listener_reconnected", 0));
+    }
+
+    realtime.listener.set_connected();
+    *backoff = MIN_BACKOFF;
+
+    println!("\nNow listening to Postgres...\n");
+
+    loop {
+        match pg_listener.try_recv().await? {
+            Some(notification) => {
+                let payload = notification.payload();
+
+                realtime.listener.received();
+
+                realtime.publish(payload);
+
+                println!("Notification:\n{payload:?}\n");
+            }
+            None => {
+                println!("Connection to Postgres lost.");
+
+                realtime
+                    .listener
+                    .set_disconnected("Connection to Postgres lost");
+
+                let _ = realtime.tx.send(resync_message("listener_lost", 0));
+
+                // Any query on the listener reconnects and re-LISTENs, if that fails too the
+                // whole listener gets recreated with a backoff
+                sqlx::query("SELECT 1").execute(&mut pg_listener).await?;
+
+                realtime.listener.set_connected();
+
+                let _ = realtime.tx.send(resync_message("listener_reconnected", 0));
+
+                println!("Reconnected to Postgres.\n");
+            }
+        }
+    }
+}
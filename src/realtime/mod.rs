@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use tokio::sync::broadcast;
+
+pub mod history;
+pub mod listener;
+pub mod presence;
+pub mod sse;
+pub mod subscription;
+pub mod ws;
+
+use history::History;
+use listener::ListenerStatus;
+use presence::Presence;
+
+const HISTORY_CAPACITY: usize = 500;
+
+#[derive(Clone)]
+pub struct Realtime {
+    pub tx: broadcast::Sender<String>,
+    pub listener: Arc<ListenerStatus>,
+    pub history: Arc<Mutex<History>>,
+    pub presence: Arc<Presence>,
+}
+
+impl Realtime {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _rx) = broadcast::channel(capacity);
+
+        Self {
+            tx,
+            listener: Arc::new(ListenerStatus::default()),
+            history: Arc::new(Mutex::new(History::new(HISTORY_CAPACITY))),
+            presence: Arc::new(Presence::default()),
+        }
+    }
+
+    // Every server event goes through here so it gets a sequence number and can be replayed
+    pub fn publish(&self, payload: &str) {
+        let mut history = self.history.lock().unwrap();
+        let message = history.record(payload);
+
+        // Sending while holding the lock keeps the broadcast in sequence order.
+        // It only fails when there are no subscribers, which is fine.
+        let _ = self.tx.send(message);
+    }
+
+    // For current state like presence that is sent again whenever it changes, keeping it out of
+    // the history leaves the replay buffer to the events clients can't afford to miss
+    pub fn publish_state(&self, payload: &str) {
+        let _ = self.tx.send(payload.to_string());
+    }
+}
+
+// Tells clients that they might have missed messages and should refetch their data
+pub fn resync_message(reason: &str, missed: u64) -> String {
+    json!({
+        "type": "resync",
+        "reason": reason,
+        "missed": missed,
+    })
+    .to_string()
+}
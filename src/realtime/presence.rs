@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::response::Result;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::handlers::judge::Judge;
+
+use super::Realtime;
+
+// A tablet that hasn't sent a heartbeat for this long is considered gone
+const STALE_AFTER: Duration = Duration::from_secs(45);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone)]
+struct Session {
+    judge_id: uuid::Uuid,
+    name: String,
+    event_id: uuid::Uuid,
+    category_id: Option<uuid::Uuid>,
+    connected_at: chrono::DateTime<chrono::Utc>,
+    last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JudgePresence {
+    judge_id: uuid::Uuid,
+    name: String,
+    event_id: uuid::Uuid,
+    category_id: Option<uuid::Uuid>,
+    connections: usize,
+    connected_at: chrono::DateTime<chrono::Utc>,
+    last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+// Judges connected over /ws, keyed by connection since a judge may have more than one tab open
+#[derive(Debug, Default)]
+pub struct Presence {
+    next_connection_id: AtomicU64,
+    sessions: Mutex<HashMap<u64, Session>>,
+}
+
+impl Presence {
+    pub fn next_connection_id(&self) -> u64 {
+        self.next_connection_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // Returns true if this is the judge's first connection, along with the judge that was
+    // authenticated on this connection before if they have no other connection left
+    fn join(&self, connection_id: u64, judge: &Judge) -> (bool, Option<uuid::Uuid>) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = chrono::Utc::now();
+        let previous = sessions.remove(&connection_id).map(|s| s.judge_id);
+        let first = !sessions.values().any(|s| s.judge_id == judge.id);
+
+        sessions.insert(
+            connection_id,
+            Session {
+                judge_id: judge.id,
+                name: judge.name.clone(),
+                event_id: judge.event_id,
+                category_id: None,
+                connected_at: now,
+                last_seen: now,
+            },
+        );
+
+        let replaced = previous.filter(|previous| {
+            *previous != judge.id && !sessions.values().any(|s| s.judge_id == *previous)
+        });
+
+        (first, replaced)
+    }
+
+    // Returns None if the session already expired and the judge has to authenticate again,
+    // otherwise whether the category shown for the judge changed
+    fn heartbeat(&self, connection_id: u64, category_id: Option<uuid::Uuid>) -> Option<bool> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let judge_id = sessions.get(&connection_id)?.judge_id;
+        let before = shown_category(&sessions, judge_id);
+
+        let session = sessions.get_mut(&connection_id)?;
+        session.last_seen = chrono::Utc::now();
+
+        if category_id.is_some() {
+            session.category_id = category_id;
+        }
+
+        Some(shown_category(&sessions, judge_id) != before)
+    }
+
+    // Returns the judge if this was their last connection
+    fn leave(&self, connection_id: u64) -> Option<uuid::Uuid> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.remove(&connection_id)?;
+
+        (!sessions.values().any(|s| s.judge_id == session.judge_id)).then_some(session.judge_id)
+    }
+
+    // Drops stale sessions and returns the judges that no longer have any connection
+    fn expire(&self) -> Vec<uuid::Uuid> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let cutoff = chrono::Utc::now() - chrono::Duration::from_std(STALE_AFTER).unwrap();
+
+        let stale: Vec<(u64, uuid::Uuid)> = sessions
+            .iter()
+            .filter(|(_, session)| session.last_seen < cutoff)
+            .map(|(id, session)| (*id, session.judge_id))
+            .collect();
+
+        for (id, _) in stale.iter() {
+            sessions.remove(id);
+        }
+
+        let mut gone: Vec<uuid::Uuid> = stale
+            .into_iter()
+            .map(|(_, judge_id)| judge_id)
+            .filter(|judge_id| !sessions.values().any(|s| s.judge_id == *judge_id))
+            .collect();
+
+        gone.sort();
+        gone.dedup();
+
+        gone
+    }
+
+    pub fn view(&self) -> Vec<JudgePresence> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut judges: HashMap<uuid::Uuid, JudgePresence> = HashMap::new();
+
+        for session in sessions.values() {
+            let judge = judges
+                .entry(session.judge_id)
+                .or_insert_with(|| JudgePresence {
+                    judge_id: session.judge_id,
+                    name: session.name.clone(),
+                    event_id: session.event_id,
+                    category_id: session.category_id,
+                    connections: 0,
+                    connected_at: session.connected_at,
+                    last_seen: session.last_seen,
+                });
+
+            judge.connections += 1;
+            judge.connected_at = judge.connected_at.min(session.connected_at);
+            judge.last_seen = judge.last_seen.max(session.last_seen);
+        }
+
+        for judge in judges.values_mut() {
+            judge.category_id = shown_category(&sessions, judge.judge_id);
+        }
+
+        let mut judges: Vec<JudgePresence> = judges.into_values().collect();
+        judges.sort_by(|a, b| a.name.cmp(&b.name));
+
+        judges
+    }
+}
+
+// The category of the judge's most recently active tablet that is on one
+fn shown_category(sessions: &HashMap<u64, Session>, judge_id: uuid::Uuid) -> Option<uuid::Uuid> {
+    sessions
+        .values()
+        .filter(|session| session.judge_id == judge_id && session.category_id.is_some())
+        .max_by_key(|session| session.last_seen)
+        .and_then(|session| session.category_id)
+}
+
+pub async fn authenticate(
+    pool: &PgPool,
+    realtime: &Realtime,
+    connection_id: u64,
+    username: &str,
+    password: &str,
+) -> Result<Judge, sqlx::Error> {
+    let judge = sqlx::query_as::<_, Judge>(
+        "SELECT * FROM judges WHERE username = ($1) AND password = ($2)",
+    )
+    .bind(username)
+    .bind(password)
+    .fetch_one(pool)
+    .await?;
+
+    let (first, replaced) = realtime.presence.join(connection_id, &judge);
+
+    if let Some(previous) = replaced {
+        set_active(pool, previous, false).await?;
+    }
+
+    if first {
+        set_active(pool, judge.id, true).await?;
+    }
+
+    broadcast(realtime);
+
+    Ok(judge)
+}
+
+pub fn heartbeat(realtime: &Realtime, connection_id: u64, category_id: Option<uuid::Uuid>) -> bool {
+    let moved = realtime.presence.heartbeat(connection_id, category_id);
+
+    // Only worth telling everyone when the judge moved to another category
+    if moved == Some(true) {
+        broadcast(realtime);
+    }
+
+    moved.is_some()
+}
+
+pub async fn disconnect(pool: &PgPool, realtime: &Realtime, connection_id: u64) {
+    if let Some(judge_id) = realtime.presence.leave(connection_id) {
+        if let Err(err) = set_active(pool, judge_id, false).await {
+            eprintln!("Failed to set is_active to FALSE: {err:?}");
+        }
+
+        broadcast(realtime);
+    }
+}
+
+// Periodically drop the tablets that stopped sending heartbeats
+pub fn spawn_sweeper(pool: PgPool, realtime: Realtime) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let gone = realtime.presence.expire();
+
+            if gone.is_empty() {
+                continue;
+            }
+
+            for judge_id in gone.iter() {
+                println!("Judge {judge_id} timed out.");
+
+                if let Err(err) = set_active(&pool, *judge_id, false).await {
+                    eprintln!("Failed to set is_active to FALSE: {err:?}");
+                }
+            }
+
+            broadcast(&realtime);
+        }
+    });
+}
+
+async fn set_active(
+    pool: &PgPool,
+    judge_id: uuid::Uuid,
+    is_active: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE judges SET is_active = ($1) WHERE id = ($2)")
+        .bind(is_active)
+        .bind(judge_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+fn broadcast(realtime: &Realtime) {
+    let payload = json!({
+        "type": "presence",
+        "judges": realtime.presence.view(),
+    });
+
+    realtime.publish_state(&payload.to_string());
+}
+
+pub async fn get_presence(
+    State(realtime): State<Realtime>,
+) -> Result<axum::Json<Vec<JudgePresence>>, AppError> {
+    Ok(axum::Json(realtime.presence.view()))
+}
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+
+use axum::extract::{Query, State};
+use axum::http;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Result;
+use futures::stream::{self, Stream};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::error::AppError;
+
+use super::subscription::{Credentials, Subscription, Topic};
+use super::{resync_message, Realtime};
+
+#[derive(Debug, Deserialize)]
+pub struct SseParams {
+    // Comma separated message types, e.g. `results_published,categories`
+    types: Option<String>,
+    // Same as `Last-Event-ID`, for the first connection where the browser can't set headers
+    last_seq: Option<u64>,
+}
+
+struct SseState {
+    rx: broadcast::Receiver<String>,
+    backlog: VecDeque<String>,
+    replayed_seq: u64,
+    subscription: Subscription,
+    types: Vec<String>,
+}
+
+impl SseState {
+    fn wants(&self, topic: &Topic) -> bool {
+        let is_resync = topic.kind.as_deref() == Some("resync");
+        let type_matches = self.types.is_empty()
+            || topic
+                .kind
+                .as_ref()
+                .is_some_and(|kind| self.types.contains(kind));
+
+        is_resync || (type_matches && self.subscription.matches(topic))
+    }
+}
+
+// One-way alternative to /ws for display screens, fed by the same broadcast channel.
+// Browsers resend the id of the last event they got in `Last-Event-ID` when reconnecting.
+pub async fn sse_handler(
+    Query(subscription): Query<Subscription>,
+    Query(params): Query<SseParams>,
+    Query(credentials): Query<Credentials>,
+    headers: http::HeaderMap,
+    State(realtime): State<Realtime>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    if !subscription.is_valid() {
+        return Err(AppError::bad_request(
+            "Judge connections must specify an event_id",
+        ));
+    }
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .or(params.last_seq);
+
+    let rx = realtime.tx.subscribe();
+    let replay = last_event_id.map(|last_seq| realtime.history.lock().unwrap().since(last_seq));
+
+    let mut backlog = VecDeque::new();
+    let mut replayed_seq = 0;
+
+    if let Some(replay) = replay {
+        if replay.gap {
+            backlog.push_back(resync_message("replay_unavailable", 0));
+        }
+
+        backlog.extend(replay.events);
+        replayed_seq = replay.latest_seq;
+    }
+
+    let types = params
+        .types
+        .map(|types| {
+            types
+                .split(',')
+                .map(|kind| kind.trim().to_string())
+                .filter(|kind| !kind.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let state = SseState {
+        rx,
+        backlog,
+        replayed_seq,
+        subscription: subscription.restrict(credentials.access()),
+        types,
+    };
+
+    let stream = stream::unfold(state, |mut state| async move {
+        loop {
+            let (msg, live) = match state.backlog.pop_front() {
+                Some(msg) => (msg, false),
+                None => match state.rx.recv().await {
+                    Ok(msg) => (msg, true),
+                    Err(RecvError::Lagged(missed)) => (resync_message("lagged", missed), true),
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+
+            let topic = Topic::from_payload(&msg);
+            let already_sent = live && topic.seq.is_some_and(|seq| seq <= state.replayed_seq);
+
+            if already_sent || !state.wants(&topic) {
+                continue;
+            }
+
+            let mut event = Event::default().data(msg);
+
+            if let Some(seq) = topic.seq {
+                event = event.id(seq.to_string());
+            }
+
+            return Some((Ok(event), state));
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
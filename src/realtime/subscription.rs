@@ -0,0 +1,203 @@
+use std::env;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+// Message types the audience display is allowed to see
+const PUBLIC_TYPES: [&str; 1] = ["results_published"];
+
+// Message types only the tabulator gets, even though they belong to an event
+const TABULATOR_TYPES: [&str; 2] = ["score_correction", "progress"];
+
+// Message types a judge only gets when they're about that judge
+const OWN_TYPES: [&str; 1] = ["score_correction_reviewed"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // Projector / big screen, only gets published results
+    #[default]
+    Audience,
+    // Judge tablet, only gets messages for its own event
+    Judge,
+    // Gets everything
+    Tabulator,
+}
+
+// What the server verified a connection is allowed to see. The role of a Subscription is only
+// what the client asked for, anyone can put `?role=tabulator` in the url.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Access {
+    #[default]
+    Audience,
+    // Authenticated with the `Auth` message, only ever sees its own event
+    Judge {
+        event_id: uuid::Uuid,
+        judge_id: uuid::Uuid,
+    },
+    Tabulator,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Credentials {
+    token: Option<String>,
+}
+
+impl Credentials {
+    // The tabulator dashboard connects with `?token=` set to TABULATOR_TOKEN, without it being
+    // configured nobody gets more than the audience view
+    pub fn access(&self) -> Access {
+        let expected = env::var("TABULATOR_TOKEN").ok();
+
+        match (expected.as_deref(), self.token.as_deref()) {
+            (Some(expected), Some(token)) if !expected.is_empty() && expected == token => {
+                Access::Tabulator
+            }
+            _ => Access::Audience,
+        }
+    }
+}
+
+// What a client wants to receive, every filter is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Subscription {
+    #[serde(default)]
+    pub role: Role,
+    pub event_id: Option<uuid::Uuid>,
+    pub category_id: Option<uuid::Uuid>,
+    pub judge_id: Option<uuid::Uuid>,
+}
+
+impl Subscription {
+    pub fn is_valid(&self) -> bool {
+        // A judge tablet has to say which event it belongs to
+        self.role != Role::Judge || self.event_id.is_some()
+    }
+
+    // Narrows the subscription down to what the connection is allowed to see
+    pub fn restrict(mut self, access: Access) -> Self {
+        match access {
+            Access::Audience => self.role = Role::Audience,
+            Access::Judge { event_id, judge_id } => {
+                self.role = Role::Judge;
+                self.event_id = Some(event_id);
+                self.judge_id = Some(judge_id);
+            }
+            Access::Tabulator => {}
+        }
+
+        self
+    }
+
+    pub fn matches(&self, topic: &Topic) -> bool {
+        // Everyone needs to know when they should refetch
+        if topic.kind.as_deref() == Some("resync") {
+            return true;
+        }
+
+        match self.role {
+            Role::Audience => {
+                let is_public = topic
+                    .kind
+                    .as_deref()
+                    .is_some_and(|kind| PUBLIC_TYPES.contains(&kind));
+
+                is_public && self.filters_match(topic)
+            }
+            // Judges never get messages that can't be tied to their event
+            Role::Judge => {
+                let kind = topic.kind.as_deref().unwrap_or_default();
+
+                if TABULATOR_TYPES.contains(&kind) {
+                    return false;
+                }
+
+                if OWN_TYPES.contains(&kind)
+                    && (self.judge_id.is_none() || self.judge_id != topic.judge_id)
+                {
+                    return false;
+                }
+
+                topic.event_id.is_some() && self.filters_match(topic)
+            }
+            Role::Tabulator => self.filters_match(topic),
+        }
+    }
+
+    // A filter only rejects a message that carries a different id, messages without
+    // that id at all (e.g. score rows have no event_id) still go through
+    fn filters_match(&self, topic: &Topic) -> bool {
+        field_matches(self.event_id, topic.event_id)
+            && field_matches(self.category_id, topic.category_id)
+            && field_matches(self.judge_id, topic.judge_id)
+    }
+}
+
+fn field_matches(wanted: Option<uuid::Uuid>, actual: Option<uuid::Uuid>) -> bool {
+    match (wanted, actual) {
+        (Some(wanted), Some(actual)) => wanted == actual,
+        _ => true,
+    }
+}
+
+// The parts of a message used for routing it to subscribers
+#[derive(Debug, Default, PartialEq)]
+pub struct Topic {
+    pub seq: Option<u64>,
+    pub kind: Option<String>,
+    pub event_id: Option<uuid::Uuid>,
+    pub category_id: Option<uuid::Uuid>,
+    pub judge_id: Option<uuid::Uuid>,
+}
+
+impl Topic {
+    pub fn from_payload(payload: &str) -> Self {
+        match serde_json::from_str::<Value>(payload) {
+            Ok(value) => Self::from_value(&value),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        // Notifications from the database triggers have the row nested in one of these
+        let nested = ["data", "record", "new"]
+            .iter()
+            .find_map(|key| value.get(key).filter(|v| v.is_object()));
+
+        let field = |name: &str| {
+            value
+                .get(name)
+                .or_else(|| nested.and_then(|row| row.get(name)))
+        };
+
+        let uuid_field = |name: &str| {
+            field(name)
+                .and_then(Value::as_str)
+                .and_then(|id| uuid::Uuid::parse_str(id).ok())
+        };
+
+        Self {
+            seq: value.get("seq").and_then(Value::as_u64),
+            kind: value
+                .get("type")
+                .or_else(|| value.get("table"))
+                .and_then(Value::as_str)
+                .map(String::from),
+            event_id: uuid_field("event_id"),
+            category_id: uuid_field("category_id"),
+            judge_id: uuid_field("judge_id"),
+        }
+    }
+}
+
+// Messages a client can send to the server, anything else it sends is ignored
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientMessage {
+    // Change what the client is subscribed to
+    Subscribe(Subscription),
+    // Judge tablets identify themselves to show up as connected
+    Auth { username: String, password: String },
+    // Sent periodically by judge tablets, along with the category they're on
+    Heartbeat { category_id: Option<uuid::Uuid> },
+}
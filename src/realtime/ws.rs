@@ -0,0 +1,202 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::{Response, Result};
+use futures::{sink::SinkExt, stream::StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, watch};
+
+use crate::error::AppError;
+
+use super::subscription::{Access, ClientMessage, Credentials, Subscription, Topic};
+use super::{presence, resync_message, Realtime};
+
+#[derive(Debug, Deserialize)]
+pub struct Resume {
+    // Sequence number of the last event the client saw before it got disconnected
+    last_seq: Option<u64>,
+}
+
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Query(subscription): Query<Subscription>,
+    Query(resume): Query<Resume>,
+    Query(credentials): Query<Credentials>,
+    State(realtime): State<Realtime>,
+    State(pool): State<PgPool>,
+) -> Result<Response, AppError> {
+    if !subscription.is_valid() {
+        return Err(AppError::bad_request(
+            "Judge connections must specify an event_id",
+        ));
+    }
+
+    let access = credentials.access();
+
+    Ok(ws.on_upgrade(move |socket| {
+        handle_socket(socket, pool, realtime, subscription, access, resume)
+    }))
+}
+
+async fn handle_socket(
+    socket: WebSocket,
+    pool: PgPool,
+    realtime: Realtime,
+    subscription: Subscription,
+    access: Access,
+    resume: Resume,
+) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // Subscribe before looking at the history so nothing falls in between,
+    // anything that ends up in both is skipped using the sequence number
+    let mut rx = realtime.tx.subscribe();
+    let replay = resume
+        .last_seq
+        .map(|last_seq| realtime.history.lock().unwrap().since(last_seq));
+
+    // The client can change its subscription while connected
+    let (sub_tx, sub_rx) = watch::channel(subscription.restrict(access));
+
+    // Replies meant only for this client, e.g. the result of authenticating
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<String>();
+
+    let connection_id = realtime.presence.next_connection_id();
+
+    // Spawn the first task that will receive broadcast messages and send the ones the client
+    // is subscribed to over the websocket.
+    let mut send_task = tokio::spawn(async move {
+        let mut replayed_seq = 0;
+
+        if let Some(replay) = replay {
+            if replay.gap {
+                let msg = resync_message("replay_unavailable", 0);
+
+                if sender.send(Message::Text(msg)).await.is_err() {
+                    return;
+                }
+            }
+
+            for msg in replay.events {
+                if !sub_rx.borrow().matches(&Topic::from_payload(&msg)) {
+                    continue;
+                }
+
+                if sender.send(Message::Text(msg)).await.is_err() {
+                    return;
+                }
+            }
+
+            replayed_seq = replay.latest_seq;
+        }
+
+        loop {
+            let msg = tokio::select! {
+                Some(reply) = reply_rx.recv() => {
+                    if sender.send(Message::Text(reply)).await.is_err() {
+                        break;
+                    }
+
+                    continue;
+                }
+                res = rx.recv() => match res {
+                    Ok(msg) => msg,
+                    // The client was too slow and messages were dropped, tell it to refetch
+                    Err(RecvError::Lagged(missed)) => resync_message("lagged", missed),
+                    Err(RecvError::Closed) => break,
+                },
+            };
+
+            let topic = Topic::from_payload(&msg);
+
+            if topic.seq.is_some_and(|seq| seq <= replayed_seq) || !sub_rx.borrow().matches(&topic)
+            {
+                continue;
+            }
+
+            if sender.send(Message::Text(msg)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Spawn a task that takes control messages from the websocket and handles them. Nothing a
+    // client sends is ever passed on to the other subscribers.
+    let task_pool = pool.clone();
+    let task_realtime = realtime.clone();
+    let mut recv_task = tokio::spawn(async move {
+        let (pool, realtime) = (task_pool, task_realtime);
+        let mut access = access;
+
+        while let Some(Ok(message)) = receiver.next().await {
+            let event = match message {
+                Message::Text(event) => event,
+                Message::Close(_) => break,
+                // Pings are answered automatically
+                _ => continue,
+            };
+
+            match serde_json::from_str::<ClientMessage>(&event) {
+                Ok(ClientMessage::Subscribe(subscription)) => {
+                    if subscription.is_valid() {
+                        println!("Subscription changed:\n{subscription:?}\n");
+
+                        sub_tx.send_replace(subscription.restrict(access));
+                    }
+                }
+                Ok(ClientMessage::Auth { username, password }) => {
+                    let reply = match presence::authenticate(
+                        &pool,
+                        &realtime,
+                        connection_id,
+                        &username,
+                        &password,
+                    )
+                    .await
+                    {
+                        Ok(judge) => {
+                            println!("Judge connected: {}\n", judge.name);
+
+                            // From now on the tablet only gets what its judge is allowed to see
+                            access = Access::Judge {
+                                event_id: judge.event_id,
+                                judge_id: judge.id,
+                            };
+
+                            sub_tx.send_modify(|subscription| {
+                                *subscription = subscription.clone().restrict(access)
+                            });
+
+                            json!({ "type": "auth_ok", "judge_id": judge.id })
+                        }
+                        Err(err) => {
+                            eprintln!("Failed to authenticate judge: {err:?}");
+
+                            json!({ "type": "auth_failed" })
+                        }
+                    };
+
+                    let _ = reply_tx.send(reply.to_string());
+                }
+                Ok(ClientMessage::Heartbeat { category_id }) => {
+                    // The session expired, the tablet has to authenticate again
+                    if !presence::heartbeat(&realtime, connection_id, category_id) {
+                        let _ = reply_tx.send(json!({ "type": "session_expired" }).to_string());
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Ignoring unknown message from client: {err:?}");
+                }
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = (&mut send_task) => recv_task.abort(),
+        _ = (&mut recv_task) => send_task.abort(),
+    };
+
+    presence::disconnect(&pool, &realtime, connection_id).await;
+}